@@ -0,0 +1,188 @@
+use super::blobs::write_entry;
+use super::checksum::sha256_hex;
+use super::common::{CONTENT_MAX_BYTES, KEY_REGEX_STR, is_key_valid};
+use super::compression::Compression;
+use super::content_type::detect_content_type;
+use super::get::{GetError, read_content};
+use super::input::{InputError, read_input};
+use super::metadata::{Metadata, MetadataError, read_metadata, unix_timestamp, write_metadata};
+use super::secrets::detect_secrets;
+use crate::clipboard::{ClipboardProvider, Selection};
+use crate::config::SecretPattern;
+use crate::crypto::{CryptoError, KeySource};
+use std::io::Error as IOError;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy)]
+pub enum AppendPosition {
+    Start,
+    End,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AppendError {
+    #[error("incorrect key provided (valid regex: {KEY_REGEX_STR})")]
+    IncorrectKeyProvided,
+    #[error(transparent)]
+    CouldntReadInput(InputError),
+    #[error("couldn't read existing content: {0}")]
+    CouldntReadExistingContent(GetError),
+    #[error(
+        "combined content is too large (actual: {0} bytes, threshold: {CONTENT_MAX_BYTES} bytes)"
+    )]
+    ContentTooLarge(usize),
+    #[error("secret pattern \"{0}\" is invalid: {1}")]
+    InvalidSecretPattern(String, regex::Error),
+    #[error(
+        "combined content looks like it contains secrets ({}); push it with --encrypt instead",
+        .0.join(", ")
+    )]
+    SecretsDetected(Vec<String>),
+    #[error("couldn't write to file in tash's data directory: {0}")]
+    CouldntWriteToFile(IOError),
+    #[error("couldn't read metadata: {0}")]
+    CouldntReadMetadata(MetadataError),
+    #[error("couldn't write metadata: {0}")]
+    CouldntWriteMetadata(MetadataError),
+    #[error(transparent)]
+    CouldntEncryptContent(CryptoError),
+    #[error("couldn't compress content: {0}")]
+    CouldntCompressContent(IOError),
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn append_content(
+    data_dir: &Path,
+    key: &str,
+    data: Option<&str>,
+    file_path: Option<&str>,
+    get_content_from_clipboard: bool,
+    clipboard_provider: &ClipboardProvider,
+    selection: Selection,
+    separator: Option<&str>,
+    position: AppendPosition,
+    key_source: &KeySource,
+    strict: bool,
+    secret_patterns: &[SecretPattern],
+    compress: bool,
+    dedupe: bool,
+    verbose: bool,
+) -> Result<(), AppendError> {
+    if !is_key_valid(key) {
+        return Err(AppendError::IncorrectKeyProvided);
+    }
+
+    let new_content = read_input(
+        data,
+        file_path,
+        get_content_from_clipboard,
+        clipboard_provider,
+        selection,
+    )
+    .map_err(AppendError::CouldntReadInput)?;
+
+    let existing_metadata =
+        read_metadata(data_dir, key).map_err(AppendError::CouldntReadMetadata)?;
+    let encryption = existing_metadata.as_ref().and_then(|m| m.encryption);
+    // existing entries stay as they are; new ones follow the config
+    let compression = match &existing_metadata {
+        Some(m) => m.compression,
+        None => compress.then_some(Compression::Zstd),
+    };
+
+    let existing_content = match read_content(data_dir, key, key_source) {
+        Ok(existing) => existing.contents,
+        Err(GetError::KeyDoesntExist) => Vec::new(),
+        Err(e) => return Err(AppendError::CouldntReadExistingContent(e)),
+    };
+
+    // the separator only goes between two pieces of content
+    let separator = match separator {
+        Some(s) if !existing_content.is_empty() => s.as_bytes(),
+        _ => &[],
+    };
+
+    let combined_len = existing_content.len() + separator.len() + new_content.len();
+    if combined_len > CONTENT_MAX_BYTES {
+        return Err(AppendError::ContentTooLarge(combined_len));
+    }
+
+    let (first, second) = match position {
+        AppendPosition::Start => (new_content, existing_content),
+        AppendPosition::End => (existing_content, new_content),
+    };
+
+    let mut content = Vec::with_capacity(combined_len);
+    content.extend_from_slice(&first);
+    content.extend_from_slice(separator);
+    content.extend_from_slice(&second);
+
+    // checked on the combined content, since a secret could've been split across both pieces
+    if encryption.is_none() {
+        let secrets = detect_secrets(&content, secret_patterns)
+            .map_err(|(name, e)| AppendError::InvalidSecretPattern(name, e))?;
+        if !secrets.is_empty() {
+            if strict {
+                return Err(AppendError::SecretsDetected(secrets));
+            }
+            eprintln!(
+                "warning: content looks like it contains secrets ({}); consider pushing it with --encrypt",
+                secrets.join(", ")
+            );
+        }
+    }
+
+    let compressed_content = match compression {
+        Some(compression) => Some(
+            compression
+                .compress(&content)
+                .map_err(AppendError::CouldntCompressContent)?,
+        ),
+        None => None,
+    };
+    let stored_content = compressed_content.as_deref().unwrap_or(&content);
+
+    // encrypted entries stay encrypted, with the same scheme
+    let encrypted_content = match encryption {
+        Some(scheme) => Some(
+            key_source
+                .encrypt(scheme, stored_content)
+                .map_err(AppendError::CouldntEncryptContent)?,
+        ),
+        None => None,
+    };
+
+    let stored_content = encrypted_content.as_deref().unwrap_or(stored_content);
+
+    write_entry(
+        data_dir,
+        key,
+        stored_content,
+        dedupe && encrypted_content.is_none(),
+    )
+    .map_err(AppendError::CouldntWriteToFile)?;
+
+    // flags set on the entry when it was pushed carry over
+    let content_type = detect_content_type(&content, None);
+    let metadata = match existing_metadata {
+        Some(existing) => Metadata {
+            content_type,
+            sha256: Some(sha256_hex(&content)),
+            size: Some(content.len() as u64),
+            updated_at: Some(unix_timestamp()),
+            ..existing
+        },
+        None => Metadata {
+            compression,
+            ..Metadata::new(&content, content_type)
+        },
+    }
+    .for_stored_content(stored_content);
+    write_metadata(data_dir, key, &metadata).map_err(AppendError::CouldntWriteMetadata)?;
+
+    if verbose {
+        println!("stashed {} bytes", content.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::checksum::sha256_hex;
+use crate::permissions::{TEMP_FILE_SUFFIX, create_private_dir, write_private_file};
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+
+/// When deduplication is enabled, content lives in this directory (inside tash's data directory),
+/// in a file named after its SHA-256 digest. Entries are symlinks to these files, so that
+/// identical content is only stored once.
+pub(super) const BLOBS_DIR: &str = ".blobs";
+
+/// Writes the content to be stored for a key, replacing whatever the key referred to earlier.
+/// With `dedupe` set, the content is stored as a blob (unless an identical one already exists),
+/// and the key is linked to it.
+pub(super) fn write_entry(
+    data_dir: &Path,
+    key: &str,
+    content: &[u8],
+    dedupe: bool,
+) -> Result<(), IOError> {
+    let entry_path = data_dir.join(key);
+    if !dedupe {
+        // writing to a temporary file first means blobs the key might link to are left alone
+        return write_private_file(&entry_path, content);
+    }
+
+    let blob_name = sha256_hex(content);
+    let blob_path = data_dir.join(BLOBS_DIR).join(&blob_name);
+    if !blob_path.is_file() {
+        create_private_dir(&data_dir.join(BLOBS_DIR))?;
+        write_private_file(&blob_path, content)?;
+    }
+
+    let mut temp_path = entry_path.as_os_str().to_owned();
+    temp_path.push(TEMP_FILE_SUFFIX);
+    let temp_path = PathBuf::from(temp_path);
+    if temp_path.is_symlink() {
+        std::fs::remove_file(&temp_path)?;
+    }
+
+    // a relative target keeps links valid if the data directory is moved
+    std::os::unix::fs::symlink(Path::new(BLOBS_DIR).join(&blob_name), &temp_path)?;
+    std::fs::rename(&temp_path, &entry_path)
+}
+
+/// Returns the name of the blob an entry links to, if the entry is stored as one.
+pub(super) fn linked_blob(entry_path: &Path) -> Option<String> {
+    let target = std::fs::read_link(entry_path).ok()?;
+    let blob_name = target.strip_prefix(BLOBS_DIR).ok()?.to_str()?;
+
+    is_blob_name(blob_name).then(|| blob_name.to_string())
+}
+
+pub(super) fn is_blob_name(name: &str) -> bool {
+    name.len() == 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
@@ -0,0 +1,215 @@
+use super::metadata::{METADATA_DIR, Metadata};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Error as IOError;
+use std::io::Read;
+
+/// Version of the JSON bundle format; bumped whenever it changes in a way older versions of tash
+/// can't read.
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BundleError {
+    #[error("couldn't create tar archive: {0}")]
+    CreateTar(IOError),
+    #[error("couldn't serialize metadata: {0}")]
+    SerializeMetadata(serde_json::Error),
+    #[error("couldn't serialize JSON bundle: {0}")]
+    SerializeJson(serde_json::Error),
+    #[error("couldn't read tar archive: {0}")]
+    ReadTar(IOError),
+    #[error("tar archive contains a file tash doesn't expect: {0}")]
+    UnexpectedTarPath(String),
+    #[error("couldn't parse metadata for key \"{0}\": {1}")]
+    ParseMetadata(String, serde_json::Error),
+    #[error("couldn't parse JSON bundle: {0}")]
+    ParseJson(serde_json::Error),
+    #[error("JSON bundle has version {0}, which this version of tash doesn't support")]
+    UnsupportedVersion(u32),
+    #[error("couldn't decode content for key \"{0}\": {1}")]
+    DecodeContent(String, base64::DecodeError),
+}
+
+/// Formats entries can be exported in. Both hold each entry's content (as it's stored, ie.
+/// encrypted entries stay encrypted) along with its metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BundleFormat {
+    /// a tar archive laid out like tash's data directory
+    Tar,
+    /// a JSON document with an object per entry
+    Json,
+}
+
+pub(super) struct BundledEntry {
+    pub(super) key: String,
+    pub(super) content: Vec<u8>,
+    pub(super) metadata: Option<Metadata>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonBundle {
+    version: u32,
+    entries: Vec<JsonBundleEntry>,
+}
+
+/// Content that's valid UTF-8 is kept as is, so that bundles of text snippets stay readable;
+/// everything else is base64 encoded.
+#[derive(Serialize, Deserialize)]
+struct JsonBundleEntry {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_base64: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+}
+
+pub(super) fn pack(entries: &[BundledEntry], format: BundleFormat) -> Result<Vec<u8>, BundleError> {
+    match format {
+        BundleFormat::Tar => pack_tar(entries),
+        BundleFormat::Json => pack_json(entries),
+    }
+}
+
+/// Reads entries from a bundle in either format; JSON bundles are told apart from tar archives by
+/// their first character.
+pub(super) fn unpack(contents: &[u8]) -> Result<Vec<BundledEntry>, BundleError> {
+    match contents.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') => unpack_json(contents),
+        _ => unpack_tar(contents),
+    }
+}
+
+fn pack_tar(entries: &[BundledEntry]) -> Result<Vec<u8>, BundleError> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for entry in entries {
+        append_tar_file(&mut builder, &entry.key, &entry.content)?;
+
+        if let Some(metadata) = &entry.metadata {
+            let contents = serde_json::to_vec(metadata).map_err(BundleError::SerializeMetadata)?;
+            let path = format!("{METADATA_DIR}/{}.json", entry.key);
+            append_tar_file(&mut builder, &path, &contents)?;
+        }
+    }
+
+    builder.into_inner().map_err(BundleError::CreateTar)
+}
+
+fn append_tar_file(
+    builder: &mut tar::Builder<Vec<u8>>,
+    path: &str,
+    contents: &[u8],
+) -> Result<(), BundleError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o600);
+    builder
+        .append_data(&mut header, path, contents)
+        .map_err(BundleError::CreateTar)
+}
+
+fn unpack_tar(contents: &[u8]) -> Result<Vec<BundledEntry>, BundleError> {
+    let mut content_by_key = BTreeMap::new();
+    let mut metadata_by_key = BTreeMap::new();
+
+    let mut archive = tar::Archive::new(contents);
+    for entry in archive.entries().map_err(BundleError::ReadTar)? {
+        let mut entry = entry.map_err(BundleError::ReadTar)?;
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .map_err(BundleError::ReadTar)?
+            .to_string_lossy()
+            .to_string();
+        let mut contents = Vec::new();
+        entry
+            .read_to_end(&mut contents)
+            .map_err(BundleError::ReadTar)?;
+
+        // keys get validated on import; only paths outside the expected layout are rejected here
+        let metadata_key = path
+            .strip_prefix(&format!("{METADATA_DIR}/"))
+            .and_then(|p| p.strip_suffix(".json"));
+        match metadata_key {
+            Some(key) if !key.contains('/') => {
+                let metadata = serde_json::from_slice(&contents)
+                    .map_err(|e| BundleError::ParseMetadata(key.to_string(), e))?;
+                metadata_by_key.insert(key.to_string(), metadata);
+            }
+            None if !path.contains('/') => {
+                content_by_key.insert(path, contents);
+            }
+            _ => return Err(BundleError::UnexpectedTarPath(path)),
+        }
+    }
+
+    let entries = content_by_key
+        .into_iter()
+        .map(|(key, content)| BundledEntry {
+            metadata: metadata_by_key.remove(&key),
+            key,
+            content,
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+fn pack_json(entries: &[BundledEntry]) -> Result<Vec<u8>, BundleError> {
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            let (content, content_base64) = match std::str::from_utf8(&entry.content) {
+                Ok(text) => (Some(text.to_string()), None),
+                Err(_) => (None, Some(BASE64.encode(&entry.content))),
+            };
+            JsonBundleEntry {
+                key: entry.key.clone(),
+                content,
+                content_base64,
+                metadata: entry.metadata.clone(),
+            }
+        })
+        .collect();
+
+    let bundle = JsonBundle {
+        version: BUNDLE_VERSION,
+        entries,
+    };
+    let mut contents = serde_json::to_vec_pretty(&bundle).map_err(BundleError::SerializeJson)?;
+    contents.push(b'\n');
+
+    Ok(contents)
+}
+
+fn unpack_json(contents: &[u8]) -> Result<Vec<BundledEntry>, BundleError> {
+    let bundle: JsonBundle = serde_json::from_slice(contents).map_err(BundleError::ParseJson)?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(BundleError::UnsupportedVersion(bundle.version));
+    }
+
+    bundle
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let content = match (entry.content, entry.content_base64) {
+                (Some(text), _) => text.into_bytes(),
+                (None, Some(encoded)) => BASE64
+                    .decode(encoded)
+                    .map_err(|e| BundleError::DecodeContent(entry.key.clone(), e))?,
+                (None, None) => Vec::new(),
+            };
+            Ok(BundledEntry {
+                key: entry.key,
+                content,
+                metadata: entry.metadata,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,28 @@
+use super::metadata::Metadata;
+use sha2::{Digest, Sha256};
+use std::io::Error as IOError;
+use std::path::Path;
+
+/// Returns the hex encoded SHA-256 digest of the content.
+pub(super) fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Returns the digest of an entry's content (or, for encrypted entries, of its stored content).
+/// Entries stashed before checksums were recorded have theirs computed.
+pub(super) fn entry_checksum(
+    data_dir: &Path,
+    key: &str,
+    metadata: Option<&Metadata>,
+) -> Result<String, IOError> {
+    if let Some(sha256) = metadata.and_then(|m| m.sha256.as_ref()) {
+        return Ok(sha256.clone());
+    }
+
+    let content = std::fs::read(data_dir.join(key))?;
+
+    Ok(sha256_hex(&content))
+}
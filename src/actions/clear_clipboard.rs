@@ -0,0 +1,98 @@
+use crate::clipboard::{ClipboardContent, ClipboardError, ClipboardKind, ClipboardProvider};
+use serde::{Deserialize, Serialize};
+use std::io::Error as IOError;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Name of the (hidden) subcommand that runs [`clear_clipboard`].
+const CLEAR_CLIPBOARD_COMMAND: &str = "clear-clipboard";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClearClipboardError {
+    #[error("couldn't read request from stdin: {0}")]
+    ReadRequest(IOError),
+    #[error("couldn't parse request: {0}")]
+    ParseRequest(serde_json::Error),
+    #[error(transparent)]
+    Clipboard(ClipboardError),
+}
+
+/// What the clipboard(s) are expected to hold once the delay is up, and what each of them held
+/// before tash wrote to it.
+#[derive(Serialize, Deserialize)]
+struct ClearRequest {
+    expected: ClipboardContent,
+    previous: Vec<(ClipboardKind, Option<ClipboardContent>)>,
+}
+
+/// Spawns a detached tash process that restores each clipboard to its previous content (or clears
+/// it) after `delay`. The clipboard contents are handed over via stdin so that they don't show up
+/// in the process list.
+pub(super) fn schedule_clipboard_clear(
+    delay: Duration,
+    expected: ClipboardContent,
+    previous: Vec<(ClipboardKind, Option<ClipboardContent>)>,
+) -> Result<(), IOError> {
+    let request = serde_json::to_vec(&ClearRequest { expected, previous })?;
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .arg(CLEAR_CLIPBOARD_COMMAND)
+        .arg("--after-ms")
+        .arg(delay.as_millis().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // keep the helper alive when the terminal that started it goes away
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&request)?;
+    }
+
+    Ok(())
+}
+
+/// Entry point for the helper process spawned by [`schedule_clipboard_clear`].
+///
+/// The clipboard is only touched if it still holds what tash put there; if the user has copied
+/// something else in the meantime, it's left alone.
+pub fn clear_clipboard(
+    clipboard_provider: &ClipboardProvider,
+    after: Duration,
+) -> Result<(), ClearClipboardError> {
+    let mut request = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut request)
+        .map_err(ClearClipboardError::ReadRequest)?;
+    let request: ClearRequest =
+        serde_json::from_slice(&request).map_err(ClearClipboardError::ParseRequest)?;
+
+    std::thread::sleep(after);
+
+    let mut clipboard = clipboard_provider
+        .connect()
+        .map_err(ClearClipboardError::Clipboard)?;
+
+    for (kind, previous) in request.previous {
+        match clipboard.read_from(kind) {
+            Ok(current) if current == request.expected => {}
+            _ => continue,
+        }
+
+        match previous {
+            Some(previous) => clipboard.write_to(kind, &previous),
+            None => clipboard.clear(kind),
+        }
+        .map_err(ClearClipboardError::Clipboard)?;
+    }
+
+    Ok(())
+}
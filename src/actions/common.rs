@@ -0,0 +1,43 @@
+use super::blobs::BLOBS_DIR;
+use super::metadata::METADATA_DIR;
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+pub(super) const KEY_REGEX_STR: &str = r"^[a-z0-9_-]{1,30}$";
+pub const CONTENT_MAX_BYTES: usize = 50 * 1024 * 1024;
+
+#[allow(clippy::expect_used)]
+static KEY_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(KEY_REGEX_STR).expect("regex is invalid"));
+
+pub fn is_key_valid(key: &str) -> bool {
+    KEY_REGEX.is_match(key)
+}
+
+/// Returns when entries were last added to, replaced in, or removed from the stash (or had their
+/// metadata change). Entries and metadata are always written to temporary files that are then
+/// moved into place, so the modification times of the directories they're in are enough to tell.
+pub fn stash_modified_at(data_dir: &Path) -> [Option<SystemTime>; 2] {
+    [data_dir, &data_dir.join(METADATA_DIR)]
+        .map(|dir| dir.metadata().and_then(|m| m.modified()).ok())
+}
+
+/// Returns the key of the entry a path (relative to the data directory) belongs to, if any. Blobs
+/// don't belong to a single entry.
+pub fn entry_key(path: &str) -> Option<String> {
+    let key = match path.split_once('/') {
+        Some((METADATA_DIR, file_name)) => file_name.strip_suffix(".json")?,
+        Some((BLOBS_DIR, _)) | Some(_) => return None,
+        None => path,
+    };
+
+    is_key_valid(key).then(|| key.to_string())
+}
+
+/// Returns the paths (relative to the data directory) of the files an entry is made up of: its
+/// content, and its metadata.
+pub fn entry_paths(key: &str) -> [String; 2] {
+    [key.to_string(), format!("{METADATA_DIR}/{key}.json")]
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use std::io::Error as IOError;
+
+/// zstd's default level; higher levels are a lot slower for little gain on the sizes stashed
+/// content usually has.
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Compression {
+    Zstd,
+}
+
+impl Compression {
+    pub(super) fn compress(&self, content: &[u8]) -> Result<Vec<u8>, IOError> {
+        match self {
+            Compression::Zstd => zstd::encode_all(content, ZSTD_LEVEL),
+        }
+    }
+
+    pub(super) fn decompress(&self, content: &[u8]) -> Result<Vec<u8>, IOError> {
+        match self {
+            Compression::Zstd => zstd::decode_all(content),
+        }
+    }
+}
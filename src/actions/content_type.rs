@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentType {
+    #[serde(rename = "text/plain")]
+    PlainText,
+    #[serde(rename = "application/json")]
+    Json,
+    #[serde(rename = "application/yaml")]
+    Yaml,
+    #[serde(rename = "image/png")]
+    Png,
+    #[serde(rename = "application/octet-stream")]
+    Binary,
+}
+
+impl ContentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::PlainText => "text/plain",
+            ContentType::Json => "application/json",
+            ContentType::Yaml => "application/yaml",
+            ContentType::Png => "image/png",
+            ContentType::Binary => "application/octet-stream",
+        }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, ContentType::Png | ContentType::Binary)
+    }
+}
+
+impl std::fmt::Display for ContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Detects the type of content by sniffing it, using the extension of the file it came from (if
+/// any) as a hint.
+pub(super) fn detect_content_type(content: &[u8], file_path: Option<&str>) -> ContentType {
+    if content.starts_with(PNG_SIGNATURE) {
+        return ContentType::Png;
+    }
+
+    let Ok(text) = std::str::from_utf8(content) else {
+        return ContentType::Binary;
+    };
+
+    if text.contains('\0') {
+        return ContentType::Binary;
+    }
+
+    let extension = file_path
+        .and_then(|p| Path::new(p).extension())
+        .map(|e| e.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("json") if is_json(text) => return ContentType::Json,
+        Some("yaml" | "yml") => return ContentType::Yaml,
+        Some("txt" | "md") => return ContentType::PlainText,
+        _ => {}
+    }
+
+    let trimmed = text.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('[')) && is_json(text) {
+        return ContentType::Json;
+    }
+
+    // YAML is a superset of a lot of plain text, so only explicit YAML documents are considered
+    if trimmed.starts_with("%YAML") || trimmed.lines().next().map(str::trim_end) == Some("---") {
+        return ContentType::Yaml;
+    }
+
+    ContentType::PlainText
+}
+
+fn is_json(text: &str) -> bool {
+    serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok()
+}
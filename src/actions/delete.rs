@@ -1,3 +1,4 @@
+use super::metadata::remove_metadata;
 use std::io::Error as IOError;
 use std::path::{Path, PathBuf};
 
@@ -31,6 +32,12 @@ pub fn delete_content_items(data_dir: &Path, keys: &Vec<String>) -> Result<(), D
         if let Err(e) = std::fs::remove_file(&stashed_file_path) {
             delete_errors.push(e);
             failed_keys.push(key.to_string());
+            continue;
+        }
+
+        if let Err(e) = remove_metadata(data_dir, key) {
+            delete_errors.push(e);
+            failed_keys.push(key.to_string());
         }
     }
 
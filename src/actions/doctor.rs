@@ -0,0 +1,361 @@
+use super::blobs::{BLOBS_DIR, is_blob_name, linked_blob};
+use super::checksum::sha256_hex;
+use super::common::{KEY_REGEX_STR, is_key_valid};
+use super::content_type::detect_content_type;
+use super::metadata::{METADATA_DIR, Metadata, MetadataError, read_metadata, write_metadata};
+use super::sync_dir::{SYNC_STATE_DIR, conflicting_version_key};
+use crate::crypto::AGE_HEADER;
+use crate::permissions::{PRIVATE_DIR_MODE, PRIVATE_FILE_MODE, TEMP_FILE_SUFFIX};
+use crate::sync::GIT_DIR;
+use std::fs::Permissions;
+use std::io::Error as IOError;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum DoctorError {
+    #[error("couldn't read tash's data directory: {0}")]
+    ReadDataDir(IOError),
+    #[error("couldn't get details of {0}: {1}")]
+    GetFileDetails(String, IOError),
+    #[error("couldn't fix permissions of {0}: {1}")]
+    SetPermissions(String, IOError),
+    #[error("couldn't read {0}: {1}")]
+    ReadFile(String, IOError),
+    #[error("couldn't remove {0}: {1}")]
+    RemoveFile(String, IOError),
+    #[error("couldn't write metadata for {0}: {1}")]
+    WriteMetadata(String, MetadataError),
+}
+
+struct Issue {
+    description: String,
+    fixable: bool,
+    fixed: bool,
+}
+
+impl Issue {
+    fn new(description: String) -> Self {
+        Self {
+            description,
+            fixable: false,
+            fixed: false,
+        }
+    }
+
+    fn fixable(description: String, fixed: bool) -> Self {
+        Self {
+            description,
+            fixable: true,
+            fixed,
+        }
+    }
+}
+
+/// Checks the stash for problems, and fixes the ones it safely can if `fix` is set. Returns
+/// whether the stash is healthy (ie, all problems found were fixed).
+pub fn run_doctor(data_dir: &Path, fix: bool) -> Result<bool, DoctorError> {
+    let mut issues = Vec::new();
+
+    check_entries(data_dir, fix, &mut issues)?;
+    check_metadata_files(data_dir, fix, &mut issues)?;
+    check_blob_files(data_dir, fix, &mut issues)?;
+    // runs last, so that files removed by the checks above aren't reported
+    check_permissions(data_dir, data_dir, fix, &mut issues)?;
+
+    if issues.is_empty() {
+        println!("no issues found");
+        return Ok(true);
+    }
+
+    for issue in &issues {
+        if issue.fixed {
+            println!("{} [fixed]", issue.description);
+        } else {
+            println!("{}", issue.description);
+        }
+    }
+
+    let issue_str = if issues.len() == 1 { "issue" } else { "issues" };
+    let fixable_count = issues.iter().filter(|i| i.fixable).count();
+    let fixed_count = issues.iter().filter(|i| i.fixed).count();
+    if fix {
+        println!("\nfound {} {issue_str} ({fixed_count} fixed)", issues.len());
+    } else if fixable_count > 0 {
+        println!(
+            "\nfound {} {issue_str}; {fixable_count} can be fixed via \"tash doctor --fix\"",
+            issues.len()
+        );
+    } else {
+        println!("\nfound {} {issue_str}", issues.len());
+    }
+
+    Ok(fixed_count == issues.len())
+}
+
+/// Everything in the data directory is expected to be a stashed file (or a link to a blob) named
+/// after a valid key, or one of the metadata and blobs directories.
+fn check_entries(data_dir: &Path, fix: bool, issues: &mut Vec<Issue>) -> Result<(), DoctorError> {
+    for path in sorted_dir_entries(data_dir)? {
+        let name = display_name(data_dir, &path);
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let file_type = std::fs::symlink_metadata(&path)
+            .map_err(|e| DoctorError::GetFileDetails(name.clone(), e))?
+            .file_type();
+
+        let tash_dirs = [METADATA_DIR, BLOBS_DIR, SYNC_STATE_DIR, GIT_DIR];
+        if tash_dirs.contains(&file_name.as_str()) && file_type.is_dir() {
+            continue;
+        }
+
+        if file_name.ends_with(TEMP_FILE_SUFFIX) && (file_type.is_file() || file_type.is_symlink())
+        {
+            issues.push(leftover_temp_file(&path, name, fix)?);
+            continue;
+        }
+
+        let links_to_blob = file_type.is_symlink() && linked_blob(&path).is_some();
+        if links_to_blob && !path.is_file() {
+            issues.push(Issue::new(format!(
+                "{name} links to content that doesn't exist"
+            )));
+            continue;
+        }
+
+        if !file_type.is_file() && !links_to_blob {
+            issues.push(Issue::new(format!(
+                "{name} is not a regular file, and isn't used by tash"
+            )));
+            continue;
+        }
+
+        if let Some(key) = conflicting_version_key(&file_name) {
+            issues.push(Issue::new(format!(
+                "{name} is a conflicting version of \"{key}\" kept by \"tash sync --dir\"; remove it once it's been dealt with"
+            )));
+            continue;
+        }
+
+        if !is_key_valid(&file_name) {
+            issues.push(Issue::new(format!(
+                "{name} is not named after a valid key (valid regex: {KEY_REGEX_STR}), and isn't used by tash"
+            )));
+            continue;
+        }
+
+        match read_metadata(data_dir, &file_name) {
+            Ok(Some(metadata)) => {
+                let Some(expected) = metadata.sha256 else {
+                    continue;
+                };
+                let mut content =
+                    std::fs::read(&path).map_err(|e| DoctorError::ReadFile(name.clone(), e))?;
+                // checksums of encrypted entries are of their stored content
+                if let Some(compression) = metadata
+                    .compression
+                    .filter(|_| metadata.encryption.is_none())
+                {
+                    match compression.decompress(&content) {
+                        Ok(c) => content = c,
+                        Err(e) => {
+                            issues.push(Issue::new(format!(
+                                "{name} couldn't be decompressed, and may be corrupted: {e}"
+                            )));
+                            continue;
+                        }
+                    }
+                }
+                if sha256_hex(&content) != expected {
+                    issues.push(Issue::new(format!(
+                        "{name} doesn't match its recorded checksum, and may be corrupted"
+                    )));
+                }
+            }
+            Ok(None) => {
+                let content =
+                    std::fs::read(&path).map_err(|e| DoctorError::ReadFile(name.clone(), e))?;
+                // the encryption scheme of encrypted content can't be recovered
+                if content.starts_with(AGE_HEADER) {
+                    issues.push(Issue::new(format!(
+                        "{name} has no metadata, and looks encrypted"
+                    )));
+                    continue;
+                }
+
+                if fix {
+                    let metadata = Metadata::new(&content, detect_content_type(&content, None));
+                    write_metadata(data_dir, &file_name, &metadata)
+                        .map_err(|e| DoctorError::WriteMetadata(name.clone(), e))?;
+                }
+                issues.push(Issue::fixable(format!("{name} has no metadata"), fix));
+            }
+            Err(e) => issues.push(Issue::new(format!("{name} has invalid metadata: {e}"))),
+        }
+    }
+
+    Ok(())
+}
+
+/// Every file in the metadata directory is expected to belong to a stashed file.
+fn check_metadata_files(
+    data_dir: &Path,
+    fix: bool,
+    issues: &mut Vec<Issue>,
+) -> Result<(), DoctorError> {
+    let metadata_dir = data_dir.join(METADATA_DIR);
+    if !metadata_dir.is_dir() {
+        return Ok(());
+    }
+
+    for path in sorted_dir_entries(&metadata_dir)? {
+        let name = display_name(data_dir, &path);
+        let is_file = std::fs::symlink_metadata(&path)
+            .map_err(|e| DoctorError::GetFileDetails(name.clone(), e))?
+            .is_file();
+        let key = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .and_then(|n| n.strip_suffix(".json").map(str::to_string));
+
+        match key {
+            // links to missing blobs are reported on their own
+            Some(key)
+                if is_file
+                    && !data_dir.join(&key).is_file()
+                    && !data_dir.join(&key).is_symlink() =>
+            {
+                if fix {
+                    std::fs::remove_file(&path)
+                        .map_err(|e| DoctorError::RemoveFile(name.clone(), e))?;
+                }
+                issues.push(Issue::fixable(
+                    format!("{name} belongs to an entry that doesn't exist"),
+                    fix,
+                ));
+            }
+            Some(_) if is_file => {}
+            _ if is_file && path.to_string_lossy().ends_with(TEMP_FILE_SUFFIX) => {
+                issues.push(leftover_temp_file(&path, name, fix)?);
+            }
+            _ => issues.push(Issue::new(format!("{name} isn't used by tash"))),
+        }
+    }
+
+    Ok(())
+}
+
+/// Every file in the blobs directory is expected to be a blob. Blobs that no entry links to are
+/// left to "tash gc".
+fn check_blob_files(
+    data_dir: &Path,
+    fix: bool,
+    issues: &mut Vec<Issue>,
+) -> Result<(), DoctorError> {
+    let blobs_dir = data_dir.join(BLOBS_DIR);
+    if !blobs_dir.is_dir() {
+        return Ok(());
+    }
+
+    for path in sorted_dir_entries(&blobs_dir)? {
+        let name = display_name(data_dir, &path);
+        let is_file = std::fs::symlink_metadata(&path)
+            .map_err(|e| DoctorError::GetFileDetails(name.clone(), e))?
+            .is_file();
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if is_file && is_blob_name(&file_name) {
+            continue;
+        }
+
+        if is_file && file_name.ends_with(TEMP_FILE_SUFFIX) {
+            issues.push(leftover_temp_file(&path, name, fix)?);
+        } else {
+            issues.push(Issue::new(format!("{name} isn't used by tash")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Temporary files are only left behind by writes that failed midway, and are safe to remove.
+fn leftover_temp_file(path: &Path, name: String, fix: bool) -> Result<Issue, DoctorError> {
+    if fix {
+        std::fs::remove_file(path).map_err(|e| DoctorError::RemoveFile(name.clone(), e))?;
+    }
+
+    Ok(Issue::fixable(
+        format!("{name} is a leftover temporary file"),
+        fix,
+    ))
+}
+
+/// Entries (and details about them) are only meant to be accessible by the current user.
+fn check_permissions(
+    data_dir: &Path,
+    path: &Path,
+    fix: bool,
+    issues: &mut Vec<Issue>,
+) -> Result<(), DoctorError> {
+    let name = display_name(data_dir, path);
+    let metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| DoctorError::GetFileDetails(name.clone(), e))?;
+
+    let expected_mode = if metadata.is_dir() {
+        PRIVATE_DIR_MODE
+    } else if metadata.is_file() {
+        PRIVATE_FILE_MODE
+    } else {
+        return Ok(());
+    };
+
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        if fix {
+            std::fs::set_permissions(path, Permissions::from_mode(expected_mode))
+                .map_err(|e| DoctorError::SetPermissions(name.clone(), e))?;
+        }
+        issues.push(Issue::fixable(
+            format!(
+                "{name} is accessible by other users (mode: {mode:o}, expected: {expected_mode:o})"
+            ),
+            fix,
+        ));
+    }
+
+    if metadata.is_dir() {
+        for path in sorted_dir_entries(path)? {
+            // git manages the modes of files in a synced stash's repository
+            if path.parent() == Some(data_dir) && path.ends_with(GIT_DIR) {
+                continue;
+            }
+            check_permissions(data_dir, &path, fix, issues)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn sorted_dir_entries(dir: &Path) -> Result<Vec<PathBuf>, DoctorError> {
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(DoctorError::ReadDataDir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(DoctorError::ReadDataDir)?;
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn display_name(data_dir: &Path, path: &Path) -> String {
+    match path.strip_prefix(data_dir) {
+        Ok(relative_path) if relative_path.as_os_str().is_empty() => "data directory".to_string(),
+        Ok(relative_path) => format!("\"{}\"", relative_path.to_string_lossy()),
+        Err(_) => format!("\"{}\"", path.to_string_lossy()),
+    }
+}
@@ -1,3 +1,4 @@
+use crate::sync::GIT_DIR;
 use std::io::Error as IOError;
 use std::path::PathBuf;
 
@@ -44,7 +45,23 @@ pub fn empty_stash(data_dir: &PathBuf, skip_confirmation: bool) -> Result<(), Em
         }
     }
 
-    std::fs::remove_dir_all(data_dir).map_err(EmptyError::DeleteDataDir)?;
+    if data_dir.join(GIT_DIR).is_dir() {
+        // a synced stash keeps its repository, so that the deletions get synced as well
+        for entry in std::fs::read_dir(data_dir).map_err(EmptyError::ReadFromDataDir)? {
+            let path = entry.map_err(EmptyError::ReadFromDataDir)?.path();
+            if path.ends_with(GIT_DIR) {
+                continue;
+            }
+
+            if path.is_dir() && !path.is_symlink() {
+                std::fs::remove_dir_all(&path).map_err(EmptyError::DeleteDataDir)?;
+            } else {
+                std::fs::remove_file(&path).map_err(EmptyError::DeleteDataDir)?;
+            }
+        }
+    } else {
+        std::fs::remove_dir_all(data_dir).map_err(EmptyError::DeleteDataDir)?;
+    }
     println!("Deleted {file_count} {entry_str}");
 
     Ok(())
@@ -0,0 +1,109 @@
+use super::bundle::{BundleError, BundleFormat, BundledEntry, pack};
+use super::list::list_keys;
+use super::metadata::{MetadataError, read_metadata};
+use crate::crypto::{CryptoError, KeySource};
+use crate::permissions::write_private_file;
+use std::io::Error as IOError;
+use std::path::Path;
+
+/// Suffix of exports that are encrypted; it's ignored when inferring an export's format.
+const ENCRYPTED_EXPORT_SUFFIX: &str = ".age";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("keys don't exist in stash: {0:?}")]
+    KeysDontExist(Vec<String>),
+    #[error("no entries to export")]
+    NothingToExport,
+    #[error("couldn't read files in tash's data directory: {0}")]
+    ReadFilesInDataDir(IOError),
+    #[error("couldn't read content for key \"{0}\": {1}")]
+    ReadContent(String, IOError),
+    #[error("couldn't read metadata for key \"{0}\": {1}")]
+    ReadMetadata(String, MetadataError),
+    #[error(transparent)]
+    CreateBundle(BundleError),
+    #[error(transparent)]
+    EncryptExport(CryptoError),
+    #[error("couldn't write export: {0}")]
+    WriteOutput(IOError),
+}
+
+/// Exports the entries for `keys` (or every entry, if none are provided) whose keys start with
+/// `prefix`. Entries are exported as they're stored, so encrypted entries stay encrypted.
+pub fn export_content(
+    data_dir: &Path,
+    keys: &[String],
+    prefix: Option<&str>,
+    output: &Path,
+    format: Option<BundleFormat>,
+    encrypt: bool,
+    key_source: &KeySource,
+) -> Result<(), ExportError> {
+    let stashed_keys = list_keys(data_dir).map_err(ExportError::ReadFilesInDataDir)?;
+
+    let nonexistent_keys = keys
+        .iter()
+        .filter(|k| !stashed_keys.contains(k))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !nonexistent_keys.is_empty() {
+        return Err(ExportError::KeysDontExist(nonexistent_keys));
+    }
+
+    let selected_keys = stashed_keys
+        .into_iter()
+        .filter(|k| keys.is_empty() || keys.contains(k))
+        .filter(|k| prefix.is_none_or(|p| k.starts_with(p)))
+        .collect::<Vec<_>>();
+    if selected_keys.is_empty() {
+        return Err(ExportError::NothingToExport);
+    }
+
+    let mut entries = Vec::with_capacity(selected_keys.len());
+    for key in selected_keys {
+        let content = std::fs::read(data_dir.join(&key))
+            .map_err(|e| ExportError::ReadContent(key.clone(), e))?;
+        let metadata =
+            read_metadata(data_dir, &key).map_err(|e| ExportError::ReadMetadata(key.clone(), e))?;
+        entries.push(BundledEntry {
+            key,
+            content,
+            metadata,
+        });
+    }
+
+    let format = format.unwrap_or_else(|| infer_format(output));
+    let mut contents = pack(&entries, format).map_err(ExportError::CreateBundle)?;
+    if encrypt {
+        contents = key_source
+            .encrypt(key_source.default_scheme(), &contents)
+            .map_err(ExportError::EncryptExport)?;
+    }
+
+    // exports hold stashed content, so they get the same permissions
+    write_private_file(output, &contents).map_err(ExportError::WriteOutput)?;
+
+    let entry_str = if entries.len() == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    println!("exported {} {entry_str}", entries.len());
+
+    Ok(())
+}
+
+fn infer_format(output: &Path) -> BundleFormat {
+    let name = output
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = name.strip_suffix(ENCRYPTED_EXPORT_SUFFIX).unwrap_or(&name);
+
+    if name.ends_with(".json") {
+        BundleFormat::Json
+    } else {
+        BundleFormat::Tar
+    }
+}
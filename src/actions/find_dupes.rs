@@ -0,0 +1,55 @@
+use super::checksum::entry_checksum;
+use super::list::list_keys;
+use super::metadata::{MetadataError, read_metadata};
+use std::collections::BTreeMap;
+use std::io::Error as IOError;
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FindDupesError {
+    #[error("couldn't read files in tash's data directory: {0}")]
+    ReadFilesInDataDir(IOError),
+    #[error("couldn't read metadata for key \"{0}\": {1}")]
+    ReadMetadata(String, MetadataError),
+    #[error("couldn't compute checksum for key \"{0}\": {1}")]
+    ComputeChecksum(String, IOError),
+}
+
+/// Prints groups of keys whose entries have the same content, one group per line. Encrypted
+/// entries are left out, since their content can't be compared without decrypting them.
+pub fn find_duplicates(data_dir: &Path) -> Result<(), FindDupesError> {
+    let keys = list_keys(data_dir).map_err(FindDupesError::ReadFilesInDataDir)?;
+
+    let mut keys_by_checksum: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for key in keys {
+        let metadata = read_metadata(data_dir, &key)
+            .map_err(|e| FindDupesError::ReadMetadata(key.clone(), e))?;
+        if metadata.as_ref().is_some_and(|m| m.encryption.is_some()) {
+            continue;
+        }
+
+        let checksum = entry_checksum(data_dir, &key, metadata.as_ref())
+            .map_err(|e| FindDupesError::ComputeChecksum(key.clone(), e))?;
+        keys_by_checksum.entry(checksum).or_default().push(key);
+    }
+
+    let mut groups = keys_by_checksum
+        .into_values()
+        .filter(|keys| keys.len() > 1)
+        .collect::<Vec<_>>();
+    groups.sort();
+
+    if groups.is_empty() {
+        println!("no duplicates found");
+        return Ok(());
+    }
+
+    let output = groups
+        .iter()
+        .map(|keys| keys.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("{output}");
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::blobs::{BLOBS_DIR, is_blob_name, linked_blob};
+use std::collections::HashSet;
+use std::io::Error as IOError;
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum GcError {
+    #[error("couldn't read tash's data directory: {0}")]
+    ReadDataDir(IOError),
+    #[error("couldn't read blobs directory: {0}")]
+    ReadBlobsDir(IOError),
+    #[error("couldn't remove blob {0}: {1}")]
+    RemoveBlob(String, IOError),
+}
+
+/// Removes blobs that no entry links to anymore (eg. ones whose entries were deleted, or
+/// overwritten with different content).
+pub fn collect_garbage(data_dir: &Path) -> Result<(), GcError> {
+    let blobs_dir = data_dir.join(BLOBS_DIR);
+    if !blobs_dir.is_dir() {
+        println!("nothing to remove");
+        return Ok(());
+    }
+
+    let mut referenced_blobs = HashSet::new();
+    for entry in std::fs::read_dir(data_dir).map_err(GcError::ReadDataDir)? {
+        let path = entry.map_err(GcError::ReadDataDir)?.path();
+        if let Some(blob_name) = linked_blob(&path) {
+            referenced_blobs.insert(blob_name);
+        }
+    }
+
+    let mut removed_count = 0;
+    let mut removed_bytes = 0;
+    for entry in std::fs::read_dir(&blobs_dir).map_err(GcError::ReadBlobsDir)? {
+        let entry = entry.map_err(GcError::ReadBlobsDir)?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // anything else in the blobs directory is left to "tash doctor"
+        if !is_blob_name(&name) || referenced_blobs.contains(&name) {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or_default();
+        std::fs::remove_file(entry.path()).map_err(|e| GcError::RemoveBlob(name.clone(), e))?;
+        removed_count += 1;
+        removed_bytes += size;
+    }
+
+    if removed_count == 0 {
+        println!("nothing to remove");
+        return Ok(());
+    }
+
+    let blob_str = if removed_count == 1 { "blob" } else { "blobs" };
+    println!("removed {removed_count} unreferenced {blob_str} ({removed_bytes} bytes)");
+
+    Ok(())
+}
@@ -1,8 +1,18 @@
-use arboard::{Clipboard, Error as ArboardError};
+use super::checksum::sha256_hex;
+use super::clear_clipboard::schedule_clipboard_clear;
+use super::content_type::{ContentType, detect_content_type};
+use super::metadata::{MetadataError, read_metadata, remove_metadata};
+use super::template::render;
+use crate::clipboard::{ClipboardContent, ClipboardError, ClipboardProvider, Selection};
+use crate::crypto::{CryptoError, KeySource};
 use std::fs::File;
 use std::io::Error as IOError;
-use std::io::Read;
+use std::io::{IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long sensitive entries stay in the clipboard when no explicit duration is provided.
+const SENSITIVE_CLEAR_AFTER: Duration = Duration::from_secs(30);
 
 #[derive(thiserror::Error, Debug)]
 pub enum GetError {
@@ -14,18 +24,57 @@ pub enum GetError {
     CouldntOpenFile(IOError),
     #[error("couldn't read file contents: {0}")]
     CouldntReadFile(IOError),
-    #[error("couldn't access system clipboard: {0}")]
-    CouldntAccessSystemClipboard(ArboardError),
-    #[error("couldn't write to system clipboard: {0}")]
-    CouldntWriteToSystemClipboard(ArboardError),
+    #[error(transparent)]
+    CouldntWriteToClipboard(ClipboardError),
+    #[error("values for the following template variables weren't provided: {}", .0.join(", "))]
+    MissingTemplateVars(Vec<String>),
+    #[error("couldn't read metadata: {0}")]
+    CouldntReadMetadata(MetadataError),
+    #[error("content is not valid text: {0}")]
+    ContentNotText(std::string::FromUtf8Error),
+    #[error("couldn't pretty print content as JSON: {0}")]
+    CouldntPrettyPrintJson(serde_json::Error),
+    #[error("refusing to output content of type {0} to a terminal (use --force to override)")]
+    BinaryContentToTerminal(ContentType),
+    #[error("couldn't write content to stdout: {0}")]
+    CouldntWriteToStdout(IOError),
+    #[error("content of type {0} can't be copied to the system clipboard")]
+    UnsupportedClipboardContent(ContentType),
+    #[error("couldn't schedule clearing of the clipboard: {0}")]
+    CouldntScheduleClipboardClear(IOError),
+    #[error(transparent)]
+    CouldntDecryptContent(CryptoError),
+    #[error("couldn't decompress content: {0}")]
+    CouldntDecompressContent(IOError),
+    #[error("content doesn't match the checksum recorded when it was stashed; it may be corrupted")]
+    ChecksumMismatch,
+}
+
+/// An entry's content, as it was stashed (ie. decrypted and decompressed).
+#[derive(Debug)]
+pub struct StashedContent {
+    pub contents: Vec<u8>,
+    pub content_type: ContentType,
+    pub sensitive: bool,
 }
 
+/// Outputs an entry's content; `served` holds content the daemon already read for it, if any.
+#[allow(clippy::too_many_arguments)]
 pub fn get_content(
     data_dir: &Path,
     key: &str,
+    served: Option<StashedContent>,
     no_output: bool,
     copy_to_clipboard: bool,
+    clipboard_provider: &ClipboardProvider,
+    selection: Selection,
+    clear_clipboard_after: Option<Duration>,
     pop: bool,
+    key_source: &KeySource,
+    vars: &[(String, String)],
+    vars_from_env: bool,
+    pretty: bool,
+    force: bool,
 ) -> Result<(), GetError> {
     let stashed_file_path = data_dir.join(PathBuf::from(key));
 
@@ -36,29 +85,155 @@ pub fn get_content(
     if no_output && !copy_to_clipboard {
         if pop {
             std::fs::remove_file(&stashed_file_path).map_err(GetError::CouldntRemoveFile)?;
+            remove_metadata(data_dir, key).map_err(GetError::CouldntRemoveFile)?;
         }
         return Ok(());
     }
 
-    let mut file = File::open(&stashed_file_path).map_err(GetError::CouldntOpenFile)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .map_err(GetError::CouldntReadFile)?;
+    let StashedContent {
+        mut contents,
+        content_type,
+        sensitive,
+    } = match served {
+        Some(content) => content,
+        None => read_content(data_dir, key, key_source)?,
+    };
+
+    // content is only treated as a template when asked to, so that entries that happen to contain
+    // {{...}} (eg. Go or Jinja templates) are output as they are
+    if !vars.is_empty() || vars_from_env {
+        let text = String::from_utf8(contents).map_err(GetError::ContentNotText)?;
+        contents = render(&text, vars, vars_from_env)
+            .map_err(GetError::MissingTemplateVars)?
+            .into_bytes();
+    }
+
+    if pretty && content_type == ContentType::Json {
+        let value: serde_json::Value =
+            serde_json::from_slice(&contents).map_err(GetError::CouldntPrettyPrintJson)?;
+        let mut pretty_contents =
+            serde_json::to_vec_pretty(&value).map_err(GetError::CouldntPrettyPrintJson)?;
+        pretty_contents.push(b'\n');
+        contents = pretty_contents;
+    }
+
+    // checked before anything is output, so that content that can't be copied isn't output either
+    let clipboard_content = if copy_to_clipboard {
+        let clipboard_content = match content_type {
+            ContentType::Png => ClipboardContent::Image(contents.clone()),
+            ContentType::Binary => {
+                return Err(GetError::UnsupportedClipboardContent(content_type));
+            }
+            ContentType::PlainText | ContentType::Json | ContentType::Yaml => {
+                ClipboardContent::Text(
+                    String::from_utf8(contents.clone()).map_err(GetError::ContentNotText)?,
+                )
+            }
+        };
+        Some(clipboard_content)
+    } else {
+        None
+    };
 
     if !no_output {
-        print!("{contents}");
+        let mut stdout = std::io::stdout();
+        if content_type.is_binary() && !force && stdout.is_terminal() {
+            return Err(GetError::BinaryContentToTerminal(content_type));
+        }
+
+        stdout
+            .write_all(&contents)
+            .map_err(GetError::CouldntWriteToStdout)?;
     }
 
-    if copy_to_clipboard {
-        let mut clipboard = Clipboard::new().map_err(GetError::CouldntAccessSystemClipboard)?;
+    if let Some(clipboard_content) = clipboard_content {
+        let mut clipboard = clipboard_provider
+            .connect()
+            .map_err(GetError::CouldntWriteToClipboard)?;
+
+        let clear_after = match clear_clipboard_after {
+            Some(d) => Some(d),
+            None if sensitive => Some(SENSITIVE_CLEAR_AFTER),
+            None => None,
+        }
+        .filter(|d| !d.is_zero());
+
+        // an empty (or unreadable) clipboard simply gets cleared later on
+        let previous_content = match clear_after {
+            Some(_) => selection
+                .kinds()
+                .iter()
+                .map(|kind| (*kind, clipboard.read_from(*kind).ok()))
+                .collect(),
+            None => Vec::new(),
+        };
 
         clipboard
-            .set_text(&contents)
-            .map_err(GetError::CouldntWriteToSystemClipboard)?;
+            .write(selection, &clipboard_content)
+            .map_err(GetError::CouldntWriteToClipboard)?;
+
+        if let Some(delay) = clear_after {
+            schedule_clipboard_clear(delay, clipboard_content, previous_content)
+                .map_err(GetError::CouldntScheduleClipboardClear)?;
+        }
     }
     if pop {
         std::fs::remove_file(&stashed_file_path).map_err(GetError::CouldntRemoveFile)?;
+        remove_metadata(data_dir, key).map_err(GetError::CouldntRemoveFile)?;
     }
 
     Ok(())
 }
+
+/// Reads an entry's content, decrypting and decompressing it as needed, and verifies it against
+/// the checksum recorded when it was stashed (which, for encrypted entries, is checked before
+/// decrypting them).
+pub fn read_content(
+    data_dir: &Path,
+    key: &str,
+    key_source: &KeySource,
+) -> Result<StashedContent, GetError> {
+    let stashed_file_path = data_dir.join(PathBuf::from(key));
+    if !stashed_file_path.exists() {
+        return Err(GetError::KeyDoesntExist);
+    }
+
+    let mut file = File::open(&stashed_file_path).map_err(GetError::CouldntOpenFile)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(GetError::CouldntReadFile)?;
+
+    let metadata = read_metadata(data_dir, key).map_err(GetError::CouldntReadMetadata)?;
+    let verify = |contents: &[u8]| match metadata.as_ref().and_then(|m| m.sha256.as_ref()) {
+        Some(expected) if *expected != sha256_hex(contents) => Err(GetError::ChecksumMismatch),
+        _ => Ok(()),
+    };
+
+    if let Some(scheme) = metadata.as_ref().and_then(|m| m.encryption) {
+        verify(&contents)?;
+        contents = key_source
+            .decrypt(scheme, &contents)
+            .map_err(GetError::CouldntDecryptContent)?;
+    }
+
+    if let Some(compression) = metadata.as_ref().and_then(|m| m.compression) {
+        contents = compression
+            .decompress(&contents)
+            .map_err(GetError::CouldntDecompressContent)?;
+    }
+
+    if metadata.as_ref().is_none_or(|m| m.encryption.is_none()) {
+        verify(&contents)?;
+    }
+
+    let content_type = match &metadata {
+        Some(m) => m.content_type,
+        None => detect_content_type(&contents, None),
+    };
+
+    Ok(StashedContent {
+        contents,
+        content_type,
+        sensitive: metadata.is_some_and(|m| m.sensitive),
+    })
+}
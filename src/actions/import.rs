@@ -0,0 +1,330 @@
+use super::blobs::write_entry;
+use super::bundle::{BundleError, BundledEntry, unpack};
+use super::common::{CONTENT_MAX_BYTES, is_key_valid};
+use super::compression::Compression;
+use super::content_type::detect_content_type;
+use super::list::list_keys;
+use super::metadata::{Metadata, MetadataError, remove_metadata, write_metadata};
+use crate::crypto::{AGE_HEADER, CryptoError, KeySource};
+use std::collections::HashSet;
+use std::io::Error as IOError;
+use std::path::Path;
+
+/// Maximum length of a key, as per `KEY_REGEX_STR`.
+const KEY_MAX_LENGTH: usize = 30;
+
+/// What to do about entries whose keys already exist in the stash (or earlier in the import).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictStrategy {
+    /// leave the existing entry alone
+    Skip,
+    /// replace the existing entry
+    Overwrite,
+    /// import the entry under the key with a numeric suffix (eg. key-1)
+    Rename,
+    /// import nothing
+    Fail,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("couldn't read file to import from: {0}")]
+    ReadSource(IOError),
+    #[error("couldn't read directory to import from: {0}")]
+    ReadDir(IOError),
+    #[error("couldn't read file \"{0}\": {1}")]
+    ReadFile(String, IOError),
+    #[error("file \"{0}\" is too large (actual: {1} bytes, threshold: {CONTENT_MAX_BYTES} bytes)")]
+    ContentTooLarge(String, usize),
+    #[error(transparent)]
+    DecryptSource(CryptoError),
+    #[error(transparent)]
+    ReadBundle(BundleError),
+    #[error("couldn't read files in tash's data directory: {0}")]
+    ReadFilesInDataDir(IOError),
+    #[error(
+        "keys already exist in the stash: {0:?} (use --on-conflict to skip, overwrite, or rename them)"
+    )]
+    KeysAlreadyExist(Vec<String>),
+    #[error("couldn't read from stdin: {0}")]
+    ReadFromStdin(IOError),
+    #[error("couldn't compress content from \"{0}\": {1}")]
+    CompressContent(String, IOError),
+    #[error("couldn't write content for key \"{0}\": {1}")]
+    WriteContent(String, IOError),
+    #[error("couldn't write metadata for key \"{0}\": {1}")]
+    WriteMetadata(String, MetadataError),
+    #[error("couldn't remove metadata for key \"{0}\": {1}")]
+    RemoveMetadata(String, IOError),
+}
+
+/// An entry to import, along with the name it had in the source (a file name, or a key in an
+/// export).
+struct Candidate {
+    source_name: String,
+    entry: BundledEntry,
+}
+
+enum PlannedAction {
+    Add,
+    Overwrite,
+    Rename(String),
+    Skip(&'static str),
+    Conflict,
+}
+
+/// Imports entries from an export (see `export_content`), or from the files in a directory. What's
+/// going to be imported is printed first, and nothing is written if `dry_run` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn import_content(
+    data_dir: &Path,
+    path: &Path,
+    on_conflict: ConflictStrategy,
+    dry_run: bool,
+    skip_confirmation: bool,
+    key_source: &KeySource,
+    compress: bool,
+    dedupe: bool,
+) -> Result<(), ImportError> {
+    let candidates = if path.is_dir() {
+        read_dir_candidates(path, compress)?
+    } else {
+        read_export_candidates(path, key_source)?
+    };
+
+    let mut taken_keys = list_keys(data_dir)
+        .map_err(ImportError::ReadFilesInDataDir)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let mut plan = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let Some(key) = sanitize_key(&candidate.source_name) else {
+            plan.push((
+                candidate.source_name.clone(),
+                PlannedAction::Skip("no valid key can be made from its name"),
+                candidate,
+            ));
+            continue;
+        };
+
+        let action = if !taken_keys.contains(&key) {
+            PlannedAction::Add
+        } else {
+            match on_conflict {
+                ConflictStrategy::Skip => PlannedAction::Skip("already exists"),
+                ConflictStrategy::Overwrite => PlannedAction::Overwrite,
+                ConflictStrategy::Rename => PlannedAction::Rename(free_key(&key, &taken_keys)),
+                ConflictStrategy::Fail => PlannedAction::Conflict,
+            }
+        };
+
+        if let PlannedAction::Rename(new_key) = &action {
+            taken_keys.insert(new_key.clone());
+        }
+        taken_keys.insert(key.clone());
+        plan.push((key, action, candidate));
+    }
+
+    if plan.is_empty() {
+        println!("nothing to import");
+        return Ok(());
+    }
+
+    for (key, action, candidate) in &plan {
+        let from = if *key == candidate.source_name {
+            String::new()
+        } else {
+            format!(" (from \"{}\")", candidate.source_name)
+        };
+        match action {
+            PlannedAction::Add => println!("add {key}{from}"),
+            PlannedAction::Overwrite => println!("overwrite {key}{from}"),
+            PlannedAction::Rename(new_key) => println!("add {new_key} ({key} already exists)"),
+            PlannedAction::Skip(reason) => println!("skip {key} ({reason})"),
+            PlannedAction::Conflict => println!("conflict {key} (already exists)"),
+        }
+    }
+
+    let conflicting_keys = plan
+        .iter()
+        .filter(|(_, action, _)| matches!(action, PlannedAction::Conflict))
+        .map(|(key, _, _)| key.clone())
+        .collect::<Vec<_>>();
+    if !conflicting_keys.is_empty() {
+        return Err(ImportError::KeysAlreadyExist(conflicting_keys));
+    }
+
+    let count = |f: fn(&PlannedAction) -> bool| plan.iter().filter(|(_, a, _)| f(a)).count();
+    let add_count = count(|a| matches!(a, PlannedAction::Add | PlannedAction::Rename(_)));
+    let overwrite_count = count(|a| matches!(a, PlannedAction::Overwrite));
+    let skip_count = count(|a| matches!(a, PlannedAction::Skip(_)));
+    println!("\n{add_count} to add, {overwrite_count} to overwrite, {skip_count} to skip");
+
+    if dry_run || add_count + overwrite_count == 0 {
+        return Ok(());
+    }
+
+    if !skip_confirmation {
+        println!("Enter \"yes\" to continue.");
+
+        let mut input = String::new();
+        std::io::stdin()
+            .read_line(&mut input)
+            .map_err(ImportError::ReadFromStdin)?;
+
+        if input.trim() != "yes" {
+            println!("import cancelled");
+            return Ok(());
+        }
+    }
+
+    for (key, action, candidate) in plan {
+        let key = match action {
+            PlannedAction::Add | PlannedAction::Overwrite => key,
+            PlannedAction::Rename(new_key) => new_key,
+            PlannedAction::Skip(_) | PlannedAction::Conflict => continue,
+        };
+        let BundledEntry {
+            content, metadata, ..
+        } = candidate.entry;
+
+        let is_encrypted = metadata.as_ref().is_some_and(|m| m.encryption.is_some());
+        write_entry(data_dir, &key, &content, dedupe && !is_encrypted)
+            .map_err(|e| ImportError::WriteContent(key.clone(), e))?;
+
+        match metadata {
+            Some(metadata) => write_metadata(data_dir, &key, &metadata)
+                .map_err(|e| ImportError::WriteMetadata(key.clone(), e))?,
+            // metadata an overwritten entry had doesn't apply anymore
+            None => remove_metadata(data_dir, &key)
+                .map_err(|e| ImportError::RemoveMetadata(key.clone(), e))?,
+        }
+    }
+
+    let imported_count = add_count + overwrite_count;
+    let entry_str = if imported_count == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    println!("imported {imported_count} {entry_str}");
+
+    Ok(())
+}
+
+/// Every regular file in the directory (apart from hidden ones) becomes an entry, as if it were
+/// stashed via "push --file-path".
+fn read_dir_candidates(dir: &Path, compress: bool) -> Result<Vec<Candidate>, ImportError> {
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(ImportError::ReadDir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ImportError::ReadDir)?;
+    paths.sort();
+
+    let mut candidates = Vec::new();
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !path.is_file() || file_name.starts_with('.') {
+            continue;
+        }
+
+        let content =
+            std::fs::read(&path).map_err(|e| ImportError::ReadFile(file_name.clone(), e))?;
+        if content.len() > CONTENT_MAX_BYTES {
+            return Err(ImportError::ContentTooLarge(file_name, content.len()));
+        }
+
+        let content_type = detect_content_type(&content, path.to_str());
+        let metadata = Metadata {
+            compression: compress.then_some(Compression::Zstd),
+            ..Metadata::new(&content, content_type)
+        };
+        let content = match metadata.compression {
+            Some(compression) => compression
+                .compress(&content)
+                .map_err(|e| ImportError::CompressContent(file_name.clone(), e))?,
+            None => content,
+        };
+
+        candidates.push(Candidate {
+            source_name: file_name.clone(),
+            entry: BundledEntry {
+                key: file_name,
+                content,
+                metadata: Some(metadata),
+            },
+        });
+    }
+
+    Ok(candidates)
+}
+
+fn read_export_candidates(
+    path: &Path,
+    key_source: &KeySource,
+) -> Result<Vec<Candidate>, ImportError> {
+    let mut contents = std::fs::read(path).map_err(ImportError::ReadSource)?;
+    if contents.starts_with(AGE_HEADER) {
+        contents = key_source
+            .decrypt(key_source.default_scheme(), &contents)
+            .map_err(ImportError::DecryptSource)?;
+    }
+
+    let entries = unpack(&contents).map_err(ImportError::ReadBundle)?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| Candidate {
+            source_name: entry.key.clone(),
+            entry,
+        })
+        .collect())
+}
+
+/// Turns a name into a valid key (eg. "My Notes.txt" into "my-notes-txt"), if possible.
+fn sanitize_key(name: &str) -> Option<String> {
+    let mut key = String::with_capacity(name.len());
+    for c in name.to_lowercase().chars() {
+        let c = if c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-' {
+            c
+        } else {
+            '-'
+        };
+        if c == '-' && key.ends_with('-') {
+            continue;
+        }
+        key.push(c);
+    }
+
+    let key = key
+        .trim_matches('-')
+        .chars()
+        .take(KEY_MAX_LENGTH)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string();
+
+    is_key_valid(&key).then_some(key)
+}
+
+/// Returns the first variant of the key with a numeric suffix that isn't taken yet.
+fn free_key(key: &str, taken_keys: &HashSet<String>) -> String {
+    let mut n = 1;
+    loop {
+        let suffix = format!("-{n}");
+        let base = key
+            .chars()
+            .take(KEY_MAX_LENGTH - suffix.len())
+            .collect::<String>();
+        let candidate = format!("{base}{suffix}");
+        if !taken_keys.contains(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
@@ -0,0 +1,64 @@
+use crate::clipboard::{ClipboardError, ClipboardProvider, Selection};
+use std::fs::File;
+use std::io::Error as IOError;
+use std::io::Read;
+
+#[derive(thiserror::Error, Debug)]
+pub enum InputError {
+    #[error("multiple input sources provided, only one can be used at a time")]
+    MultipleInputSourcesProvided,
+    #[error("couldn't open file: {0}")]
+    CouldntOpenFile(IOError),
+    #[error("couldn't read file contents: {0}")]
+    CouldntReadFile(IOError),
+    #[error("couldn't read from stdin: {0}")]
+    CouldntReadFromStdin(IOError),
+    #[error(transparent)]
+    CouldntReadFromClipboard(ClipboardError),
+}
+
+/// Reads content from exactly one of the supported input sources: a string, a file, the system
+/// clipboard, or stdin (used when no other source is provided).
+///
+/// Images read from the clipboard are encoded as PNG.
+pub(super) fn read_input(
+    data: Option<&str>,
+    file_path: Option<&str>,
+    get_content_from_clipboard: bool,
+    clipboard_provider: &ClipboardProvider,
+    selection: Selection,
+) -> Result<Vec<u8>, InputError> {
+    let content = match (data, file_path, get_content_from_clipboard) {
+        (Some(_), Some(_), _) | (Some(_), None, true) | (None, Some(_), true) => {
+            return Err(InputError::MultipleInputSourcesProvided);
+        }
+        (None, Some(p), false) => {
+            let mut file = File::open(p).map_err(InputError::CouldntOpenFile)?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)
+                .map_err(InputError::CouldntReadFile)?;
+
+            contents
+        }
+        (None, None, true) => {
+            let mut clipboard = clipboard_provider
+                .connect()
+                .map_err(InputError::CouldntReadFromClipboard)?;
+
+            clipboard
+                .read(selection)
+                .map_err(InputError::CouldntReadFromClipboard)?
+                .into_bytes()
+        }
+        (None, None, false) => {
+            let mut buffer = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut buffer)
+                .map_err(InputError::CouldntReadFromStdin)?;
+            buffer
+        }
+        (Some(c), None, false) => c.as_bytes().to_vec(),
+    };
+
+    Ok(content)
+}
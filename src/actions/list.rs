@@ -1,44 +1,185 @@
+use super::checksum::entry_checksum;
+use super::common::is_key_valid;
+use super::content_type::ContentType;
+use super::metadata::{MetadataError, read_metadata};
+use serde::{Deserialize, Serialize};
 use std::io::Error as IOError;
-use std::path::PathBuf;
+use std::path::Path;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ListError {
     #[error("couldn't read files in tash's data directory: {0}")]
     ReadFilesInDataDir(IOError),
-    #[error("couldn't get file from tash's data directory: {0}")]
-    GetFileFromDataDir(IOError),
-    #[error("couldn't get the name of a file in tash's data directory; path: {0}")]
-    GetFileStem(String),
+    #[error("couldn't get details of a file in tash's data directory: {0}")]
+    GetFileDetails(IOError),
+    #[error("couldn't read metadata for key \"{0}\": {1}")]
+    ReadMetadata(String, MetadataError),
+    #[error("couldn't compute checksum for key \"{0}\": {1}")]
+    ComputeChecksum(String, IOError),
 }
 
-pub fn list_content(data_dir: &PathBuf) -> Result<(), ListError> {
-    let mut stashed_files = Vec::new();
-    for entry in std::fs::read_dir(data_dir).map_err(ListError::ReadFilesInDataDir)? {
-        let entry = entry.map_err(ListError::GetFileFromDataDir)?;
-        let path = entry.path();
-        if path.is_file() {
-            let f = path
-                .file_stem()
-                .ok_or(ListError::GetFileStem(
-                    path.to_string_lossy().to_ascii_lowercase(),
-                ))?
-                .to_owned();
-            stashed_files.push(f);
-        }
+/// Details of a stashed entry, as shown by "ls --long".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryDetails {
+    pub key: String,
+    /// size of the content (if known; it isn't for encrypted entries), before it was compressed
+    pub size: Option<u64>,
+    /// size of the content as it's stored in tash's data directory
+    pub stored_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub content_type: Option<ContentType>,
+    pub sensitive: bool,
+    pub encrypted: bool,
+    pub compressed: bool,
+}
+
+/// Lists entries in the stash; `served` holds entries the daemon already listed, if any.
+pub fn list_content(
+    data_dir: &Path,
+    long: bool,
+    hash: bool,
+    served: Option<Vec<EntryDetails>>,
+) -> Result<(), ListError> {
+    let entries = match served {
+        Some(entries) => entries,
+        None => list_entries(data_dir, hash)?,
+    };
+    if entries.is_empty() {
+        return Ok(());
     }
 
-    if stashed_files.is_empty() {
+    if !long {
+        let keys = entries.into_iter().map(|e| e.key).collect::<Vec<_>>();
+        println!("{}", keys.join("\n"));
         return Ok(());
     }
 
-    stashed_files.sort();
+    // columns: key, size, checksum (if asked for), content type, flags
+    let mut rows = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let flags = [
+            (entry.sensitive, "sensitive"),
+            (entry.encrypted, "encrypted"),
+            (entry.compressed, "compressed"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect::<Vec<_>>()
+        .join(",");
+
+        // compressed entries show their size as well as the size they take up on disk
+        let size = match entry.size {
+            Some(content_size) if entry.compressed => {
+                format!("{content_size} ({} stored)", entry.stored_size)
+            }
+            _ => entry.stored_size.to_string(),
+        };
+
+        let mut row = vec![entry.key, size];
+        if hash {
+            row.push(entry.sha256.unwrap_or("-".to_string()));
+        }
+        row.push(
+            entry
+                .content_type
+                .map(|t| t.to_string())
+                .unwrap_or("-".to_string()),
+        );
+        row.push(flags);
+        rows.push(row);
+    }
+
+    let column_count = rows.first().map(Vec::len).unwrap_or_default();
+    let widths = (0..column_count)
+        .map(|i| rows.iter().map(|r| r[i].len()).max().unwrap_or_default())
+        .collect::<Vec<_>>();
 
-    let output = stashed_files
+    let output = rows
         .iter()
-        .map(|os_str| os_str.to_string_lossy())
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (value, width))| {
+                    // sizes are right aligned
+                    if i == 1 {
+                        format!("{value:>width$}")
+                    } else {
+                        format!("{value:<width$}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
         .collect::<Vec<_>>()
         .join("\n");
     println!("{output}");
 
     Ok(())
 }
+
+/// Returns details of every entry in the stash, sorted by key. Checksums are only included if
+/// asked for, since entries stashed without one need to be read to compute it.
+pub fn list_entries(data_dir: &Path, checksums: bool) -> Result<Vec<EntryDetails>, ListError> {
+    list_keys(data_dir)
+        .map_err(ListError::ReadFilesInDataDir)?
+        .into_iter()
+        .map(|key| entry_details(data_dir, key, checksums))
+        .collect()
+}
+
+/// Returns the (sorted) keys of every entry in the stash. Files that aren't named after valid keys
+/// aren't entries ("tash doctor" reports them).
+pub fn list_keys(data_dir: &Path) -> Result<Vec<String>, IOError> {
+    let mut keys = Vec::new();
+    for entry in std::fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        let key = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if path.is_file() && is_key_valid(&key) {
+            keys.push(key);
+        }
+    }
+    keys.sort();
+
+    Ok(keys)
+}
+
+/// Returns details of the entry stored under `key` (which is expected to exist).
+pub fn entry_details(
+    data_dir: &Path,
+    key: String,
+    checksum: bool,
+) -> Result<EntryDetails, ListError> {
+    let stored_size = data_dir
+        .join(&key)
+        .metadata()
+        .map_err(ListError::GetFileDetails)?
+        .len();
+    let metadata =
+        read_metadata(data_dir, &key).map_err(|e| ListError::ReadMetadata(key.clone(), e))?;
+    let sha256 = if checksum {
+        Some(
+            entry_checksum(data_dir, &key, metadata.as_ref())
+                .map_err(|e| ListError::ComputeChecksum(key.clone(), e))?,
+        )
+    } else {
+        None
+    };
+
+    Ok(EntryDetails {
+        size: metadata.as_ref().and_then(|m| m.size),
+        stored_size,
+        sha256,
+        content_type: metadata.as_ref().map(|m| m.content_type),
+        sensitive: metadata.as_ref().is_some_and(|m| m.sensitive),
+        encrypted: metadata.as_ref().is_some_and(|m| m.encryption.is_some()),
+        compressed: metadata.as_ref().is_some_and(|m| m.compression.is_some()),
+        key,
+    })
+}
@@ -0,0 +1,129 @@
+use super::checksum::sha256_hex;
+use super::compression::Compression;
+use super::content_type::ContentType;
+use crate::crypto::EncryptionScheme;
+use crate::permissions::{create_private_dir, write_private_file};
+use serde::{Deserialize, Serialize};
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Metadata for entries lives in this directory (inside tash's data directory), in a JSON file
+/// per key.
+pub(super) const METADATA_DIR: &str = ".meta";
+
+#[derive(thiserror::Error, Debug)]
+pub enum MetadataError {
+    #[error("couldn't create metadata directory: {0}")]
+    CreateDir(IOError),
+    #[error("couldn't read metadata file: {0}")]
+    Read(IOError),
+    #[error("couldn't parse metadata: {0}")]
+    Parse(serde_json::Error),
+    #[error("couldn't serialize metadata: {0}")]
+    Serialize(serde_json::Error),
+    #[error("couldn't write metadata file: {0}")]
+    Write(IOError),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Metadata {
+    pub(super) content_type: ContentType,
+    /// sensitive entries are cleared from the clipboard some time after being copied to it
+    #[serde(default)]
+    pub(super) sensitive: bool,
+    /// content is stored encrypted if this is set (content_type refers to the decrypted content)
+    #[serde(default)]
+    pub(super) encryption: Option<EncryptionScheme>,
+    /// hex encoded SHA-256 digest of the content; for encrypted entries, it's the digest of the
+    /// stored (encrypted) content, so that it can't be used to guess what they hold
+    #[serde(default)]
+    pub(super) sha256: Option<String>,
+    /// content is stored compressed if this is set (it's compressed before being encrypted)
+    #[serde(default)]
+    pub(super) compression: Option<Compression>,
+    /// size of the content in bytes, before it's compressed (not recorded for encrypted entries)
+    #[serde(default)]
+    pub(super) size: Option<u64>,
+    /// when the content was last written (as a unix timestamp)
+    #[serde(default)]
+    pub(super) updated_at: Option<u64>,
+}
+
+impl Metadata {
+    pub(super) fn new(content: &[u8], content_type: ContentType) -> Self {
+        Self {
+            content_type,
+            sensitive: false,
+            encryption: None,
+            sha256: Some(sha256_hex(content)),
+            compression: None,
+            size: Some(content.len() as u64),
+            updated_at: Some(unix_timestamp()),
+        }
+    }
+
+    /// Replaces the checksum of an encrypted entry's content with that of `stored_content` (what
+    /// actually gets written to disk), and drops its size.
+    pub(super) fn for_stored_content(self, stored_content: &[u8]) -> Self {
+        if self.encryption.is_none() {
+            return self;
+        }
+
+        Self {
+            sha256: Some(sha256_hex(stored_content)),
+            size: None,
+            ..self
+        }
+    }
+}
+
+pub(super) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn metadata_path(data_dir: &Path, key: &str) -> PathBuf {
+    data_dir.join(METADATA_DIR).join(format!("{key}.json"))
+}
+
+/// Returns `None` for entries that don't have metadata (eg. ones stashed by older versions of
+/// tash).
+pub(super) fn read_metadata(data_dir: &Path, key: &str) -> Result<Option<Metadata>, MetadataError> {
+    let path = metadata_path(data_dir, key);
+    let contents = match std::fs::read(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(MetadataError::Read(e)),
+    };
+
+    let metadata = serde_json::from_slice(&contents).map_err(MetadataError::Parse)?;
+
+    Ok(Some(metadata))
+}
+
+pub(super) fn write_metadata(
+    data_dir: &Path,
+    key: &str,
+    metadata: &Metadata,
+) -> Result<(), MetadataError> {
+    let metadata_dir = data_dir.join(METADATA_DIR);
+    if !metadata_dir.exists() {
+        create_private_dir(&metadata_dir).map_err(MetadataError::CreateDir)?;
+    }
+
+    let contents = serde_json::to_vec(metadata).map_err(MetadataError::Serialize)?;
+    write_private_file(&metadata_path(data_dir, key), &contents).map_err(MetadataError::Write)?;
+
+    Ok(())
+}
+
+pub(super) fn remove_metadata(data_dir: &Path, key: &str) -> Result<(), IOError> {
+    match std::fs::remove_file(metadata_path(data_dir, key)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
@@ -1,11 +1,47 @@
+mod append;
+mod blobs;
+mod bundle;
+mod checksum;
+mod clear_clipboard;
+mod common;
+mod compression;
+mod content_type;
 mod delete;
+mod doctor;
 mod empty;
+mod export;
+mod find_dupes;
+mod gc;
 mod get;
+mod import;
+mod input;
 mod list;
+mod metadata;
 mod push;
+mod run;
+mod search;
+mod secrets;
+mod sync_dir;
+mod template;
+mod vars;
 
+pub use append::*;
+pub use bundle::BundleFormat;
+pub use clear_clipboard::*;
+pub use common::{CONTENT_MAX_BYTES, entry_key, entry_paths, is_key_valid, stash_modified_at};
+pub use content_type::ContentType;
 pub use delete::*;
+pub use doctor::*;
 pub use empty::*;
+pub use export::*;
+pub use find_dupes::*;
+pub use gc::*;
 pub use get::*;
+pub use import::*;
+pub use input::*;
 pub use list::*;
 pub use push::*;
+pub use run::*;
+pub use search::*;
+pub use sync_dir::*;
+pub use vars::*;
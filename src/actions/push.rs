@@ -1,35 +1,41 @@
-use arboard::{Clipboard, Error as ArboardError};
-use regex::Regex;
-use std::fs::File;
+use super::blobs::write_entry;
+use super::common::{CONTENT_MAX_BYTES, KEY_REGEX_STR, is_key_valid};
+use super::compression::Compression;
+use super::content_type::detect_content_type;
+use super::input::{InputError, read_input};
+use super::metadata::{Metadata, MetadataError, write_metadata};
+use super::secrets::detect_secrets;
+use crate::clipboard::{ClipboardProvider, Selection};
+use crate::config::SecretPattern;
+use crate::crypto::{CryptoError, KeySource};
 use std::io::Error as IOError;
-use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
-const KEY_REGEX_STR: &str = r"^[a-z0-9_-]{1,30}$";
-const CONTENT_MAX_BYTES: usize = 50 * 1024 * 1024;
-
 #[derive(thiserror::Error, Debug)]
 pub enum PushError {
     #[error("incorrect key provided (valid regex: {KEY_REGEX_STR})")]
     IncorrectKeyProvided,
     #[error("key already exists in the stash")]
     KeyAlreadyExists,
-    #[error("multiple input sources provided, only one can be used at a time")]
-    MultipleInputSourcesProvided,
-    #[error("couldn't open file in tash's data directory: {0}")]
-    CouldntOpenFile(IOError),
-    #[error("couldn't read file contents: {0}")]
-    CouldntReadFile(IOError),
-    #[error("couldn't read from stdin: {0}")]
-    CouldntReadFromStdin(IOError),
-    #[error("couldn't access system clipboard: {0}")]
-    CouldntAccessSystemClipboard(ArboardError),
-    #[error("couldn't read from system clipboard: {0}")]
-    CouldntReadFromSystemClipboard(ArboardError),
+    #[error(transparent)]
+    CouldntReadInput(InputError),
     #[error("content is too large (actual: {0} bytes, threshold: {CONTENT_MAX_BYTES} bytes)")]
     ContentTooLarge(usize),
+    #[error("secret pattern \"{0}\" is invalid: {1}")]
+    InvalidSecretPattern(String, regex::Error),
+    #[error(
+        "content looks like it contains secrets ({}); stash it with --encrypt instead",
+        .0.join(", ")
+    )]
+    SecretsDetected(Vec<String>),
     #[error("couldn't write to file in tash's data directory: {0}")]
     CouldntWriteToFile(IOError),
+    #[error("couldn't compress content: {0}")]
+    CouldntCompressContent(IOError),
+    #[error(transparent)]
+    CouldntEncryptContent(CryptoError),
+    #[error("couldn't write metadata: {0}")]
+    CouldntWriteMetadata(MetadataError),
     #[error("couldn't echo content back to stdout: {0}")]
     CouldntEchoContent(std::string::FromUtf8Error),
 }
@@ -41,72 +47,147 @@ pub fn push_content(
     data: Option<&str>,
     file_path: Option<&str>,
     get_content_from_clipboard: bool,
+    clipboard_provider: &ClipboardProvider,
+    selection: Selection,
     prevent_overwrite: bool,
+    sensitive: bool,
+    encrypt: bool,
+    compress: bool,
+    key_source: &KeySource,
+    strict: bool,
+    secret_patterns: &[SecretPattern],
+    dedupe: bool,
     echo: bool,
     verbose: bool,
 ) -> Result<(), PushError> {
-    #[allow(clippy::expect_used)]
-    let re = Regex::new(KEY_REGEX_STR).expect("regex is invalid");
-    if !re.is_match(key) {
-        return Err(PushError::IncorrectKeyProvided);
+    validate_key(data_dir, key, prevent_overwrite)?;
+
+    let content = read_input(
+        data,
+        file_path,
+        get_content_from_clipboard,
+        clipboard_provider,
+        selection,
+    )
+    .map_err(PushError::CouldntReadInput)?;
+
+    let compressed_size = store_content(
+        data_dir,
+        key,
+        &content,
+        file_path,
+        sensitive,
+        encrypt,
+        compress,
+        key_source,
+        strict,
+        secret_patterns,
+        dedupe,
+    )?;
+
+    if verbose {
+        match compressed_size {
+            Some(size) => println!("stashed {} bytes ({size} bytes compressed)", content.len()),
+            None => println!("stashed {} bytes", content.len()),
+        }
+    }
+
+    if echo {
+        let content_str = String::from_utf8(content).map_err(PushError::CouldntEchoContent)?;
+        print!("{content_str}");
     }
 
-    let stash_file_path = data_dir.join(PathBuf::from(key));
+    Ok(())
+}
 
-    if prevent_overwrite && stash_file_path.exists() {
-        return Err(PushError::KeyAlreadyExists);
+/// Checks that content can be stashed under a key.
+pub fn validate_key(data_dir: &Path, key: &str, prevent_overwrite: bool) -> Result<(), PushError> {
+    if !is_key_valid(key) {
+        return Err(PushError::IncorrectKeyProvided);
     }
 
-    let content = match (data, file_path, get_content_from_clipboard) {
-        (Some(_), Some(_), true) | (Some(_), Some(_), false) | (Some(_), None, true) => {
-            return Err(PushError::MultipleInputSourcesProvided);
-        }
-        (None, Some(p), false) => {
-            let mut file = File::open(p).map_err(PushError::CouldntOpenFile)?;
-            let mut contents = Vec::new();
-            file.read_to_end(&mut contents)
-                .map_err(PushError::CouldntReadFile)?;
+    if prevent_overwrite && data_dir.join(PathBuf::from(key)).exists() {
+        return Err(PushError::KeyAlreadyExists);
+    }
 
-            contents
-        }
-        (None, None, true) => {
-            let mut clipboard =
-                Clipboard::new().map_err(PushError::CouldntAccessSystemClipboard)?;
-            let clipboard_text = clipboard
-                .get_text()
-                .map_err(PushError::CouldntReadFromSystemClipboard)?;
-
-            clipboard_text.into_bytes()
-        }
-        (None, Some(_), true) => todo!(),
-        (None, None, false) => {
-            let mut buffer = Vec::new();
-            std::io::stdin()
-                .read_to_end(&mut buffer)
-                .map_err(PushError::CouldntReadFromStdin)?;
-            buffer
-        }
-        (Some(c), None, false) => c.as_bytes().to_vec(),
-    };
+    Ok(())
+}
 
+/// Stashes content under a key (which is expected to have been validated via `validate_key`),
+/// compressing and encrypting it as asked. `file_path` is only used as a hint for detecting the
+/// content's type. Returns the size of the compressed content, if it was compressed.
+#[allow(clippy::too_many_arguments)]
+pub fn store_content(
+    data_dir: &Path,
+    key: &str,
+    content: &[u8],
+    file_path: Option<&str>,
+    sensitive: bool,
+    encrypt: bool,
+    compress: bool,
+    key_source: &KeySource,
+    strict: bool,
+    secret_patterns: &[SecretPattern],
+    dedupe: bool,
+) -> Result<Option<usize>, PushError> {
     if content.len() > CONTENT_MAX_BYTES {
         return Err(PushError::ContentTooLarge(content.len()));
     }
 
-    let mut stash_file = File::create(&stash_file_path).map_err(PushError::CouldntOpenFile)?;
+    if !encrypt {
+        let secrets = detect_secrets(content, secret_patterns)
+            .map_err(|(name, e)| PushError::InvalidSecretPattern(name, e))?;
+        if !secrets.is_empty() {
+            if strict {
+                return Err(PushError::SecretsDetected(secrets));
+            }
+            eprintln!(
+                "warning: content looks like it contains secrets ({}); consider stashing it with --encrypt",
+                secrets.join(", ")
+            );
+        }
+    }
 
-    stash_file
-        .write_all(&content)
-        .map_err(PushError::CouldntWriteToFile)?;
+    let metadata = Metadata {
+        sensitive,
+        encryption: encrypt.then(|| key_source.default_scheme()),
+        compression: compress.then_some(Compression::Zstd),
+        ..Metadata::new(content, detect_content_type(content, file_path))
+    };
 
-    if verbose {
-        println!("stashed {} bytes", content.len());
-    }
+    // compressing encrypted content wouldn't achieve much, so it goes first
+    let compressed_content = match metadata.compression {
+        Some(compression) => Some(
+            compression
+                .compress(content)
+                .map_err(PushError::CouldntCompressContent)?,
+        ),
+        None => None,
+    };
+    let stored_content = compressed_content.as_deref().unwrap_or(content);
 
-    if echo {
-        let content_str = String::from_utf8(content).map_err(PushError::CouldntEchoContent)?;
-        print!("{content_str}");
-    }
+    let encrypted_content = match metadata.encryption {
+        Some(scheme) => Some(
+            key_source
+                .encrypt(scheme, stored_content)
+                .map_err(PushError::CouldntEncryptContent)?,
+        ),
+        None => None,
+    };
 
-    Ok(())
+    let stored_content = encrypted_content.as_deref().unwrap_or(stored_content);
+
+    // encrypted content differs every time it's encrypted, so there's nothing to deduplicate
+    write_entry(
+        data_dir,
+        key,
+        stored_content,
+        dedupe && encrypted_content.is_none(),
+    )
+    .map_err(PushError::CouldntWriteToFile)?;
+
+    let metadata = metadata.for_stored_content(stored_content);
+    write_metadata(data_dir, key, &metadata).map_err(PushError::CouldntWriteMetadata)?;
+
+    Ok(compressed_content.map(|c| c.len()))
 }
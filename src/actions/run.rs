@@ -0,0 +1,122 @@
+use super::get::{GetError, read_content};
+use super::template::render;
+use crate::crypto::KeySource;
+use std::io::Error as IOError;
+use std::path::Path;
+use std::process::Command;
+
+const SHELL_ENV_VAR: &str = "TASH_SHELL";
+const DEFAULT_SHELL: &str = "sh";
+
+#[derive(thiserror::Error, Debug)]
+pub enum RunError {
+    #[error(transparent)]
+    CouldntReadContent(GetError),
+    #[error("content is not valid text: {0}")]
+    ContentNotText(std::string::FromUtf8Error),
+    #[error("values for the following template variables weren't provided: {}", .0.join(", "))]
+    MissingTemplateVars(Vec<String>),
+    #[error("TASH_SHELL value is invalid: {0}")]
+    ShellEnvVarInvalid(std::env::VarError),
+    #[error("couldn't read from stdin: {0}")]
+    ReadFromStdin(IOError),
+    #[error("couldn't run command using shell \"{0}\": {1}")]
+    CouldntRunCommand(String, IOError),
+}
+
+/// A stashed command, rendered and ready to be run.
+#[derive(Debug)]
+pub struct StashedCommand {
+    key: String,
+    command: String,
+    args: Vec<String>,
+    shell: String,
+    skip_confirmation: bool,
+}
+
+/// Reads the content stored under `key` as a command to run via `shell -c`. It's run via
+/// [`StashedCommand::run`], which is meant to happen once the stash is closed, since the command
+/// may well use tash itself.
+#[allow(clippy::too_many_arguments)]
+pub fn run_content(
+    data_dir: &Path,
+    key: &str,
+    args: &[String],
+    shell: Option<&str>,
+    skip_confirmation: bool,
+    key_source: &KeySource,
+    vars: &[(String, String)],
+    vars_from_env: bool,
+) -> Result<StashedCommand, RunError> {
+    let contents = read_content(data_dir, key, key_source)
+        .map_err(RunError::CouldntReadContent)?
+        .contents;
+    let mut command = String::from_utf8(contents).map_err(RunError::ContentNotText)?;
+
+    if !vars.is_empty() || vars_from_env {
+        command = render(&command, vars, vars_from_env).map_err(RunError::MissingTemplateVars)?;
+    }
+
+    let shell = match shell {
+        Some(s) => s.to_string(),
+        None => match std::env::var(SHELL_ENV_VAR) {
+            Ok(s) => s,
+            Err(std::env::VarError::NotPresent) => DEFAULT_SHELL.to_string(),
+            Err(e) => return Err(RunError::ShellEnvVarInvalid(e)),
+        },
+    };
+
+    Ok(StashedCommand {
+        key: key.to_string(),
+        command,
+        args: args.to_vec(),
+        shell,
+        skip_confirmation,
+    })
+}
+
+impl StashedCommand {
+    /// Runs the command, and returns its exit code (or 1, if running it wasn't confirmed).
+    ///
+    /// The key is passed as `$0` and the arguments as the positional parameters, so stashed
+    /// commands can refer to them as `$1`, `$@`, etc.
+    pub fn run(self) -> Result<i32, RunError> {
+        let StashedCommand {
+            key,
+            command,
+            args,
+            shell,
+            skip_confirmation,
+        } = self;
+
+        // prompts go to stderr so that the command's stdout stays clean
+        if !skip_confirmation {
+            eprintln!("This will run the following command using \"{shell}\":\n\n{command}\n");
+            eprintln!("Enter \"yes\" to continue.");
+
+            let mut input = String::new();
+
+            std::io::stdin()
+                .read_line(&mut input)
+                .map_err(RunError::ReadFromStdin)?;
+
+            let input = input.trim();
+            if input != "yes" {
+                eprintln!("run cancelled");
+                // scripts need to be able to tell a cancelled run apart from a successful one
+                return Ok(1);
+            }
+        }
+
+        let status = Command::new(&shell)
+            .arg("-c")
+            .arg(&command)
+            .arg(&key)
+            .args(&args)
+            .status()
+            .map_err(|e| RunError::CouldntRunCommand(shell.clone(), e))?;
+
+        // a command terminated by a signal doesn't have an exit code
+        Ok(status.code().unwrap_or(1))
+    }
+}
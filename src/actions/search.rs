@@ -0,0 +1,53 @@
+use super::get::{GetError, read_content};
+use super::list::{EntryDetails, ListError, list_entries};
+use crate::crypto::KeySource;
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SearchError {
+    #[error(transparent)]
+    ListEntries(ListError),
+    #[error("couldn't read content for key \"{0}\": {1}")]
+    ReadContent(String, Box<GetError>),
+}
+
+/// Returns the entries whose keys, or text content, contain `query` (ignoring case). Encrypted
+/// entries are only matched by their keys, so that searching doesn't need them to be decrypted;
+/// so are sensitive ones, unless `include_sensitive` is set.
+pub fn search_entries(
+    data_dir: &Path,
+    query: &str,
+    key_source: &KeySource,
+    include_sensitive: bool,
+) -> Result<Vec<EntryDetails>, SearchError> {
+    let query = query.to_lowercase();
+    let entries = list_entries(data_dir, false).map_err(SearchError::ListEntries)?;
+
+    let mut matches = Vec::new();
+    for entry in entries {
+        if entry.key.contains(&query) {
+            matches.push(entry);
+            continue;
+        }
+
+        let is_text = entry.content_type.is_none_or(|t| !t.is_binary());
+        if entry.encrypted || (entry.sensitive && !include_sensitive) || !is_text {
+            continue;
+        }
+
+        let content = read_content(data_dir, &entry.key, key_source)
+            .map_err(|e| SearchError::ReadContent(entry.key.clone(), Box::new(e)))?;
+        if content.content_type.is_binary() {
+            continue;
+        }
+
+        if String::from_utf8_lossy(&content.contents)
+            .to_lowercase()
+            .contains(&query)
+        {
+            matches.push(entry);
+        }
+    }
+
+    Ok(matches)
+}
@@ -0,0 +1,40 @@
+use crate::config::SecretPattern;
+use regex::bytes::Regex;
+
+const BUILTIN_SECRET_PATTERNS: [(&str, &str); 4] = [
+    ("AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "GitHub token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,255}|github_pat_[A-Za-z0-9_]{82})\b",
+    ),
+    ("private key", r"-----BEGIN (?:[A-Z0-9]+ )*PRIVATE KEY-----"),
+    (
+        "JSON web token",
+        r"\beyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+    ),
+];
+
+/// Returns the names of the kinds of secrets (built-in ones, and the ones in `custom_patterns`)
+/// found in the content. Custom patterns that aren't valid regexes are returned as an error,
+/// along with their names.
+pub(super) fn detect_secrets(
+    content: &[u8],
+    custom_patterns: &[SecretPattern],
+) -> Result<Vec<String>, (String, regex::Error)> {
+    let builtin_patterns = BUILTIN_SECRET_PATTERNS
+        .iter()
+        .map(|(name, pattern)| (name.to_string(), pattern.to_string()));
+    let custom_patterns = custom_patterns
+        .iter()
+        .map(|p| (p.name.clone(), p.pattern.clone()));
+
+    let mut found = Vec::new();
+    for (name, pattern) in builtin_patterns.chain(custom_patterns) {
+        let re = Regex::new(&pattern).map_err(|e| (name.clone(), e))?;
+        if re.is_match(content) && !found.contains(&name) {
+            found.push(name);
+        }
+    }
+
+    Ok(found)
+}
@@ -0,0 +1,296 @@
+use super::blobs::write_entry;
+use super::checksum::sha256_hex;
+use super::common::is_key_valid;
+use super::list::list_keys;
+use super::metadata::{
+    MetadataError, read_metadata, remove_metadata, unix_timestamp, write_metadata,
+};
+use crate::permissions::{create_private_dir, write_private_file};
+use crate::sync::SyncPreference;
+use crate::vault::KEY_FILE;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+
+/// What was synced with each directory last time lives in this directory (inside tash's data
+/// directory), in a JSON file per directory.
+pub(super) const SYNC_STATE_DIR: &str = ".sync";
+/// Versions of entries that lost a conflict are kept next to them (along with their metadata),
+/// named `<KEY><SUFFIX><TIMESTAMP>`.
+const CONFLICT_SUFFIX: &str = ".conflict-";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SyncDirError {
+    #[error("couldn't create directory to sync with: {0}")]
+    CreateDir(IOError),
+    #[error("couldn't resolve path of directory to sync with: {0}")]
+    ResolveDir(IOError),
+    #[error("can't sync the stash with its own data directory")]
+    SameDir,
+    #[error(
+        "directory to sync with holds an encrypted stash; encrypted stashes can only be synced via git"
+    )]
+    EncryptedDir,
+    #[error("this stash is encrypted; encrypted stashes can only be synced via git")]
+    EncryptedStash,
+    #[error("couldn't read sync state: {0}")]
+    ReadState(IOError),
+    #[error("couldn't parse sync state: {0}")]
+    ParseState(serde_json::Error),
+    #[error("couldn't serialize sync state: {0}")]
+    SerializeState(serde_json::Error),
+    #[error("couldn't write sync state: {0}")]
+    WriteState(IOError),
+    #[error("couldn't list entries in {0}: {1}")]
+    ListEntries(String, IOError),
+    #[error("couldn't read content for key \"{0}\": {1}")]
+    ReadContent(String, IOError),
+    #[error("couldn't read metadata for key \"{0}\": {1}")]
+    ReadMetadata(String, MetadataError),
+    #[error("couldn't write content for key \"{0}\": {1}")]
+    WriteContent(String, IOError),
+    #[error("couldn't write metadata for key \"{0}\": {1}")]
+    WriteMetadata(String, MetadataError),
+    #[error("couldn't keep conflicting version of key \"{0}\": {1}")]
+    WriteConflict(String, IOError),
+    #[error("couldn't remove key \"{0}\": {1}")]
+    RemoveEntry(String, IOError),
+}
+
+/// The hashes entries (content and metadata) had when the stash was last synced with a directory;
+/// they tell which side changed an entry (or deleted it) since then.
+#[derive(Default, Serialize, Deserialize)]
+struct SyncState {
+    dir: PathBuf,
+    entries: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Local,
+    Dir,
+}
+
+/// Syncs the stash both ways with another tash data directory (eg. one on a network share).
+/// Entries are compared by the hashes of their stored content and metadata. Entries changed on
+/// both sides since the last sync keep the version `prefer` points to (or the one updated last),
+/// and the other version is kept in a file named `<KEY>.conflict-<TIMESTAMP>` (with metadata of
+/// the same name) on the side it was on.
+pub fn sync_with_dir(
+    data_dir: &Path,
+    dir: &Path,
+    prefer: Option<SyncPreference>,
+    dedupe: bool,
+) -> Result<(), SyncDirError> {
+    if !dir.exists() {
+        create_private_dir(dir).map_err(SyncDirError::CreateDir)?;
+    }
+    let dir = std::fs::canonicalize(dir).map_err(SyncDirError::ResolveDir)?;
+    if std::fs::canonicalize(data_dir).is_ok_and(|d| d == dir) {
+        return Err(SyncDirError::SameDir);
+    }
+    if dir.join(KEY_FILE).exists() {
+        return Err(SyncDirError::EncryptedDir);
+    }
+
+    let state_path = data_dir.join(SYNC_STATE_DIR).join(format!(
+        "{}.json",
+        &sha256_hex(dir.as_os_str().as_encoded_bytes())[..16]
+    ));
+    let base = read_state(&state_path)?;
+    let local = entry_hashes(data_dir)?;
+    let remote = entry_hashes(&dir)?;
+
+    let keys = local
+        .keys()
+        .chain(remote.keys())
+        .chain(base.entries.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+
+    let now = unix_timestamp();
+    let mut state = SyncState {
+        dir: dir.clone(),
+        entries: BTreeMap::new(),
+    };
+    let (mut pulled, mut pushed, mut conflicts) = (0, 0, 0);
+    for key in keys {
+        let (local_hash, remote_hash) = (local.get(&key), remote.get(&key));
+        let base_hash = base.entries.get(&key);
+
+        let source = if local_hash == remote_hash {
+            None
+        } else if local_hash == base_hash {
+            Some(Side::Dir)
+        } else if remote_hash == base_hash {
+            Some(Side::Local)
+        } else if local_hash.is_none() {
+            // entries changed on one side and deleted on the other are kept
+            Some(Side::Dir)
+        } else if remote_hash.is_none() {
+            Some(Side::Local)
+        } else {
+            let winner = match prefer {
+                Some(SyncPreference::Local) => Side::Local,
+                Some(SyncPreference::Remote) => Side::Dir,
+                None if updated_at(&dir, &key)? > updated_at(data_dir, &key)? => Side::Dir,
+                None => Side::Local,
+            };
+            let (loser_dir, winner_str, loser_str) = match winner {
+                Side::Local => (dir.as_path(), "this stash's", "the directory's"),
+                Side::Dir => (data_dir, "the directory's", "this stash's"),
+            };
+            let conflict_name = format!("{key}{CONFLICT_SUFFIX}{now}");
+            // the version is kept as stored, so its metadata is needed to make sense of it (eg. if
+            // it's compressed or encrypted)
+            let content = std::fs::read(loser_dir.join(&key))
+                .map_err(|e| SyncDirError::ReadContent(key.clone(), e))?;
+            write_private_file(&loser_dir.join(&conflict_name), &content)
+                .map_err(|e| SyncDirError::WriteConflict(key.clone(), e))?;
+            if let Some(metadata) = read_metadata(loser_dir, &key)
+                .map_err(|e| SyncDirError::ReadMetadata(key.clone(), e))?
+            {
+                write_metadata(loser_dir, &conflict_name, &metadata)
+                    .map_err(|e| SyncDirError::WriteMetadata(key.clone(), e))?;
+            }
+            println!(
+                "conflict {key}: kept {winner_str} version; {loser_str} version was saved as {conflict_name}"
+            );
+            conflicts += 1;
+            Some(winner)
+        };
+
+        match source {
+            None => {}
+            Some(Side::Dir) => {
+                let change = apply_change(&dir, data_dir, &key, dedupe)?;
+                println!("pulled {key} ({change})");
+                pulled += 1;
+            }
+            Some(Side::Local) => {
+                let change = apply_change(data_dir, &dir, &key, false)?;
+                println!("pushed {key} ({change})");
+                pushed += 1;
+            }
+        }
+
+        let synced_hash = match source {
+            Some(Side::Dir) => remote_hash,
+            _ => local_hash,
+        };
+        if let Some(hash) = synced_hash {
+            state.entries.insert(key, hash.clone());
+        }
+    }
+
+    write_state(data_dir, &state_path, &state)?;
+
+    if pulled + pushed == 0 {
+        println!("already in sync");
+    } else {
+        let conflict_str = if conflicts == 1 {
+            "conflict"
+        } else {
+            "conflicts"
+        };
+        println!("\npulled {pulled}, pushed {pushed}, {conflicts} {conflict_str}");
+    }
+
+    Ok(())
+}
+
+/// Returns the key of the entry a conflicting version was kept for, if the file name belongs to
+/// one.
+pub(super) fn conflicting_version_key(file_name: &str) -> Option<&str> {
+    let (key, timestamp) = file_name.rsplit_once(CONFLICT_SUFFIX)?;
+    let is_timestamp = !timestamp.is_empty() && timestamp.chars().all(|c| c.is_ascii_digit());
+
+    (is_timestamp && is_key_valid(key)).then_some(key)
+}
+
+/// Makes the entry for `key` in `to_dir` match the one in `from_dir`. Returns what changed.
+fn apply_change(
+    from_dir: &Path,
+    to_dir: &Path,
+    key: &str,
+    dedupe: bool,
+) -> Result<&'static str, SyncDirError> {
+    let from_path = from_dir.join(key);
+    let existed = to_dir.join(key).is_file();
+    if !from_path.is_file() {
+        if to_dir.join(key).is_symlink() || existed {
+            std::fs::remove_file(to_dir.join(key))
+                .map_err(|e| SyncDirError::RemoveEntry(key.to_string(), e))?;
+        }
+        remove_metadata(to_dir, key).map_err(|e| SyncDirError::RemoveEntry(key.to_string(), e))?;
+        return Ok("deleted");
+    }
+
+    let content =
+        std::fs::read(&from_path).map_err(|e| SyncDirError::ReadContent(key.to_string(), e))?;
+    let metadata =
+        read_metadata(from_dir, key).map_err(|e| SyncDirError::ReadMetadata(key.to_string(), e))?;
+
+    let is_encrypted = metadata.as_ref().is_some_and(|m| m.encryption.is_some());
+    write_entry(to_dir, key, &content, dedupe && !is_encrypted)
+        .map_err(|e| SyncDirError::WriteContent(key.to_string(), e))?;
+    match metadata {
+        Some(metadata) => write_metadata(to_dir, key, &metadata)
+            .map_err(|e| SyncDirError::WriteMetadata(key.to_string(), e))?,
+        None => remove_metadata(to_dir, key)
+            .map_err(|e| SyncDirError::RemoveEntry(key.to_string(), e))?,
+    }
+
+    Ok(if existed { "changed" } else { "added" })
+}
+
+/// Returns the SHA-256 digest of the stored content and the metadata of every entry in a data
+/// directory, so that changes to either (eg. an entry being marked as sensitive) get synced.
+fn entry_hashes(data_dir: &Path) -> Result<BTreeMap<String, String>, SyncDirError> {
+    let keys = list_keys(data_dir)
+        .map_err(|e| SyncDirError::ListEntries(data_dir.to_string_lossy().to_string(), e))?;
+
+    let mut hashes = BTreeMap::new();
+    for key in keys {
+        let mut hashed = std::fs::read(data_dir.join(&key))
+            .map_err(|e| SyncDirError::ReadContent(key.clone(), e))?;
+        // metadata is hashed as tash would write it, so that the way it's formatted doesn't matter
+        if let Some(metadata) =
+            read_metadata(data_dir, &key).map_err(|e| SyncDirError::ReadMetadata(key.clone(), e))?
+        {
+            hashed.push(b'\n');
+            hashed.extend(serde_json::to_vec(&metadata).map_err(|e| {
+                SyncDirError::ReadMetadata(key.clone(), MetadataError::Serialize(e))
+            })?);
+        }
+        hashes.insert(key, sha256_hex(&hashed));
+    }
+
+    Ok(hashes)
+}
+
+/// Entries without a recorded update time count as the oldest ones.
+fn updated_at(data_dir: &Path, key: &str) -> Result<u64, SyncDirError> {
+    let metadata =
+        read_metadata(data_dir, key).map_err(|e| SyncDirError::ReadMetadata(key.to_string(), e))?;
+
+    Ok(metadata.and_then(|m| m.updated_at).unwrap_or_default())
+}
+
+fn read_state(path: &Path) -> Result<SyncState, SyncDirError> {
+    let contents = match std::fs::read(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SyncState::default()),
+        Err(e) => return Err(SyncDirError::ReadState(e)),
+    };
+
+    serde_json::from_slice(&contents).map_err(SyncDirError::ParseState)
+}
+
+fn write_state(data_dir: &Path, path: &Path, state: &SyncState) -> Result<(), SyncDirError> {
+    create_private_dir(&data_dir.join(SYNC_STATE_DIR)).map_err(SyncDirError::WriteState)?;
+    let contents = serde_json::to_vec_pretty(state).map_err(SyncDirError::SerializeState)?;
+
+    write_private_file(path, &contents).map_err(SyncDirError::WriteState)
+}
@@ -0,0 +1,67 @@
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+const PLACEHOLDER_REGEX_STR: &str = r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}";
+
+fn placeholder_regex() -> Regex {
+    #[allow(clippy::expect_used)]
+    Regex::new(PLACEHOLDER_REGEX_STR).expect("regex is invalid")
+}
+
+/// Returns the names of all placeholders (eg. `{{host}}`) in the content, in order of first
+/// appearance.
+pub(super) fn get_placeholders(content: &str) -> Vec<String> {
+    let mut placeholders: Vec<String> = Vec::new();
+    for captures in placeholder_regex().captures_iter(content) {
+        let name = &captures[1];
+        if !placeholders.iter().any(|p| p == name) {
+            placeholders.push(name.to_string());
+        }
+    }
+
+    placeholders
+}
+
+/// Replaces placeholders in the content with values from `vars`, falling back to environment
+/// variables of the same name if `vars_from_env` is set. Returns the names of the variables that
+/// couldn't be resolved as an error.
+pub(super) fn render(
+    content: &str,
+    vars: &[(String, String)],
+    vars_from_env: bool,
+) -> Result<String, Vec<String>> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    let mut missing = Vec::new();
+    for name in get_placeholders(content) {
+        // later values take precedence, same as when a shell variable is set twice
+        let value = vars
+            .iter()
+            .rev()
+            .find(|(k, _)| k == &name)
+            .map(|(_, v)| v.clone())
+            .or_else(|| {
+                if vars_from_env {
+                    std::env::var(&name).ok()
+                } else {
+                    None
+                }
+            });
+
+        match value {
+            Some(v) => {
+                values.insert(name, v);
+            }
+            None => missing.push(name),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    let rendered = placeholder_regex().replace_all(content, |captures: &Captures| {
+        values.get(&captures[1]).cloned().unwrap_or_default()
+    });
+
+    Ok(rendered.into_owned())
+}
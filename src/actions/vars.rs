@@ -0,0 +1,32 @@
+use super::get::{GetError, read_content};
+use super::template::get_placeholders;
+use crate::crypto::KeySource;
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum VarsError {
+    #[error(transparent)]
+    CouldntReadContent(GetError),
+    #[error("content is not valid text: {0}")]
+    ContentNotText(std::string::FromUtf8Error),
+}
+
+pub fn list_template_vars(
+    data_dir: &Path,
+    key: &str,
+    key_source: &KeySource,
+) -> Result<(), VarsError> {
+    let contents = read_content(data_dir, key, key_source)
+        .map_err(VarsError::CouldntReadContent)?
+        .contents;
+    let contents = String::from_utf8(contents).map_err(VarsError::ContentNotText)?;
+
+    let placeholders = get_placeholders(&contents);
+    if placeholders.is_empty() {
+        return Ok(());
+    }
+
+    println!("{}", placeholders.join("\n"));
+
+    Ok(())
+}
@@ -1,7 +1,17 @@
+use crate::actions::{BundleFormat, ConflictStrategy};
+use crate::clipboard::Selection;
+use crate::completions::{Shell, complete_keys};
+use crate::sync::SyncPreference;
 use clap::{Parser, Subcommand};
+use clap_complete::ArgValueCandidates;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
-#[command(about("s[tash] content that you can access later"))]
+#[command(
+    about("s[tash] content that you can access later"),
+    disable_help_subcommand = true
+)]
 pub struct Args {
     /// action to take
     #[command(subcommand)]
@@ -10,24 +20,139 @@ pub struct Args {
 
 #[derive(Subcommand, Debug)]
 pub enum Action {
+    /// Restore/clear the clipboard after a delay (used internally by "get --clear-after")
+    #[command(hide = true)]
+    ClearClipboard {
+        /// delay in milliseconds
+        #[arg(long = "after-ms", value_name = "MILLISECONDS")]
+        after_ms: u64,
+    },
+    /// Remove an unlocked session once it expires (used internally by "unlock")
+    #[command(hide = true)]
+    ExpireSession {
+        /// delay in milliseconds
+        #[arg(long = "after-ms", value_name = "MILLISECONDS")]
+        after_ms: u64,
+    },
+    /// Append content to an entry (creates the entry if it doesn't exist)
+    Append {
+        /// key of the entry to append to (needs to conform to the regex ^[a-z0-9_-]{1,30}$)
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
+        key: String,
+        /// content to append
+        #[arg(short = 'd', long = "data", value_name = "STRING")]
+        data: Option<String>,
+        /// path of the file whose contents to append
+        #[arg(short = 'f', long = "file-path", value_name = "STRING")]
+        file_path: Option<String>,
+        /// whether to get content from system clipboard
+        #[arg(short = 'c', long = "clipboard")]
+        get_content_from_clipboard: bool,
+        /// clipboard to use (defaults to the "selection" config setting, or "clipboard")
+        #[arg(
+            long = "selection",
+            value_name = "SELECTION",
+            requires = "get_content_from_clipboard"
+        )]
+        selection: Option<Selection>,
+        /// string to insert between the existing and the new content
+        #[arg(short = 's', long = "separator", value_name = "STRING")]
+        separator: Option<String>,
+        /// refuse to stash unencrypted content that looks like it contains secrets (instead of warning)
+        #[arg(long = "strict")]
+        strict: bool,
+        /// whether to output information about the stashed file
+        #[arg(short = 'v', long = "verbose")]
+        verbose: bool,
+    },
+    /// Print a script that sets up shell completions (including keys in the stash)
+    Completions {
+        /// shell to set up completions for
+        #[arg(value_name = "SHELL")]
+        shell: Shell,
+    },
+    /// Serve ls and get from an in-memory index over a Unix socket, for fast repeated access (eg. by editors)
+    Daemon,
+    /// Turn an encrypted stash back into a regular one
+    DecryptStash,
     /// Delete one or more content items
     Delete {
         /// keys to delete
-        #[arg(value_name = "KEY")]
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
         keys: Vec<String>,
     },
+    /// Check the stash for problems (eg. stray files, missing metadata, loose file permissions)
+    Doctor {
+        /// fix the problems that can be fixed safely
+        #[arg(long = "fix")]
+        fix: bool,
+    },
     /// Empty entire stash
     Empty {
         /// skip confirmation
         #[arg(short = 'y', long = "yes")]
         skip_confirmation: bool,
     },
+    /// Encrypt the entire stash (content and keys) with a passphrase
+    EncryptStash,
+    /// Export entries (content and metadata) to a tar archive or a JSON bundle
+    Export {
+        /// keys to export (every entry is exported if none are provided)
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
+        keys: Vec<String>,
+        /// path of the file to write the export to
+        #[arg(short = 'o', long = "output", value_name = "PATH")]
+        output: PathBuf,
+        /// format of the export (inferred from the output's extension if not provided)
+        #[arg(short = 'f', long = "format", value_name = "FORMAT")]
+        format: Option<BundleFormat>,
+        /// only export entries whose keys start with this prefix
+        #[arg(short = 'p', long = "prefix", value_name = "PREFIX")]
+        prefix: Option<String>,
+        /// encrypt the export (with the configured age identity, or a passphrase otherwise)
+        #[arg(long = "encrypt")]
+        encrypt: bool,
+    },
+    /// Import entries from an export, or from the files in a directory
+    Import {
+        /// export (tar archive or JSON bundle) or directory to import from
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+        /// what to do about entries whose keys already exist in the stash
+        #[arg(
+            short = 'c',
+            long = "on-conflict",
+            value_name = "STRATEGY",
+            default_value = "fail"
+        )]
+        on_conflict: ConflictStrategy,
+        /// only show what would be imported
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+        /// skip confirmation
+        #[arg(short = 'y', long = "yes")]
+        skip_confirmation: bool,
+    },
     /// List stashed content keys
-    LS,
+    LS {
+        /// show details (size in bytes, content type) for each entry
+        #[arg(short = 'l', long = "long")]
+        long: bool,
+        /// show the SHA-256 digest of each entry's content as well (of the encrypted content, for
+        /// encrypted entries)
+        #[arg(long = "hash", requires = "long")]
+        hash: bool,
+    },
+    /// Lock an encrypted stash that was unlocked earlier
+    Lock,
+    /// List keys whose entries have identical content
+    FindDupes,
+    /// Remove stored content that no entry refers to anymore (when deduplication is enabled)
+    Gc,
     /// Get content from stash
     Get {
         /// key used when stashing content
-        #[arg(value_name = "KEY")]
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
         key: String,
         /// don't output content to stdout
         #[arg(short = 'n', long = "no-output")]
@@ -35,9 +160,80 @@ pub enum Action {
         /// whether to copy content to system clipboard
         #[arg(short = 'c', long = "clipboard")]
         copy_to_clipboard: bool,
+        /// clipboard to use (defaults to the "selection" config setting, or "clipboard")
+        #[arg(
+            long = "selection",
+            value_name = "SELECTION",
+            requires = "copy_to_clipboard"
+        )]
+        selection: Option<Selection>,
+        /// restore the clipboard's previous content after this duration, eg. 30s, 5m (defaults to 30s for sensitive entries; 0 disables it)
+        #[arg(
+            long = "clear-after",
+            value_name = "DURATION",
+            value_parser = parse_duration,
+            requires = "copy_to_clipboard"
+        )]
+        clear_clipboard_after: Option<Duration>,
         /// whether to remove content from stash
         #[arg(short = 'p', long = "pop")]
         pop: bool,
+        /// value for a template variable (eg. --var host=localhost for {{host}}); content is only
+        /// rendered as a template if this, or --vars-from-env, is passed
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_template_var)]
+        vars: Vec<(String, String)>,
+        /// read values for template variables from environment variables of the same name
+        #[arg(long = "vars-from-env")]
+        vars_from_env: bool,
+        /// pretty print content if it's JSON
+        #[arg(long = "pretty")]
+        pretty: bool,
+        /// output binary content even if stdout is a terminal
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Print the man page for tash, or write man pages for every command to a directory
+    Man {
+        /// directory to write man pages to (eg. /usr/local/share/man/man1)
+        #[arg(short = 'd', long = "dir", value_name = "PATH")]
+        dir: Option<PathBuf>,
+    },
+    /// Run a Model Context Protocol server over stdio, with tools for listing, searching, getting, and pushing entries
+    Mcp {
+        /// hand out (and allow overwriting) the content of sensitive and encrypted entries
+        #[arg(long = "allow-sensitive")]
+        allow_sensitive: bool,
+    },
+    /// Prepend content to an entry (creates the entry if it doesn't exist)
+    Prepend {
+        /// key of the entry to prepend to (needs to conform to the regex ^[a-z0-9_-]{1,30}$)
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
+        key: String,
+        /// content to prepend
+        #[arg(short = 'd', long = "data", value_name = "STRING")]
+        data: Option<String>,
+        /// path of the file whose contents to prepend
+        #[arg(short = 'f', long = "file-path", value_name = "STRING")]
+        file_path: Option<String>,
+        /// whether to get content from system clipboard
+        #[arg(short = 'c', long = "clipboard")]
+        get_content_from_clipboard: bool,
+        /// clipboard to use (defaults to the "selection" config setting, or "clipboard")
+        #[arg(
+            long = "selection",
+            value_name = "SELECTION",
+            requires = "get_content_from_clipboard"
+        )]
+        selection: Option<Selection>,
+        /// string to insert between the new and the existing content
+        #[arg(short = 's', long = "separator", value_name = "STRING")]
+        separator: Option<String>,
+        /// refuse to stash unencrypted content that looks like it contains secrets (instead of warning)
+        #[arg(long = "strict")]
+        strict: bool,
+        /// whether to output information about the stashed file
+        #[arg(short = 'v', long = "verbose")]
+        verbose: bool,
     },
     /// Stash content
     Push {
@@ -53,9 +249,28 @@ pub enum Action {
         /// whether to get content from system clipboard
         #[arg(short = 'c', long = "clipboard")]
         get_content_from_clipboard: bool,
+        /// clipboard to use (defaults to the "selection" config setting, or "clipboard")
+        #[arg(
+            long = "selection",
+            value_name = "SELECTION",
+            requires = "get_content_from_clipboard"
+        )]
+        selection: Option<Selection>,
         /// fail if key already exists in the stash
         #[arg(short = 'p', long = "prevent-overwrite")]
         prevent_overwrite: bool,
+        /// mark content as sensitive (it'll be cleared from the clipboard 30s after being copied to it)
+        #[arg(long = "sensitive")]
+        sensitive: bool,
+        /// encrypt content (with the configured age identity, or a passphrase otherwise)
+        #[arg(long = "encrypt")]
+        encrypt: bool,
+        /// compress content (via zstd) before storing it
+        #[arg(long = "compress")]
+        compress: bool,
+        /// refuse to stash unencrypted content that looks like it contains secrets (instead of warning)
+        #[arg(long = "strict")]
+        strict: bool,
         /// echo contents back to stdout
         #[arg(short = 'e', long = "echo")]
         echo: bool,
@@ -63,4 +278,115 @@ pub enum Action {
         #[arg(short = 'v', long = "verbose")]
         verbose: bool,
     },
+    /// Serve a local HTTP API for listing, getting, pushing, deleting, and searching entries
+    Serve {
+        /// address to listen on
+        #[arg(
+            short = 'b',
+            long = "bind",
+            value_name = "ADDRESS",
+            default_value = "127.0.0.1:7433"
+        )]
+        bind: String,
+    },
+    /// Sync the stash with a git remote (set up via sync init), or with another data directory
+    #[command(args_conflicts_with_subcommands = true)]
+    Sync {
+        #[command(subcommand)]
+        command: Option<SyncCommand>,
+        /// sync with this tash data directory (eg. on a network share) instead of the git remote
+        #[arg(long = "dir", value_name = "PATH")]
+        dir: Option<PathBuf>,
+        /// which version to keep for entries that changed both locally and remotely
+        #[arg(long = "prefer", value_name = "SIDE", global = true)]
+        prefer: Option<SyncPreference>,
+    },
+    /// Run stashed content as a shell command
+    Run {
+        /// key used when stashing content
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
+        key: String,
+        /// arguments to pass to the command (available as $1, $2, etc.)
+        #[arg(value_name = "ARGS", last = true)]
+        args: Vec<String>,
+        /// shell to run the command with (defaults to $TASH_SHELL, or "sh" if that's not set)
+        #[arg(short = 's', long = "shell", value_name = "SHELL")]
+        shell: Option<String>,
+        /// skip confirmation
+        #[arg(short = 'y', long = "yes")]
+        skip_confirmation: bool,
+        /// value for a template variable (eg. --var host=localhost for {{host}}); content is only
+        /// rendered as a template if this, or --vars-from-env, is passed
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_template_var)]
+        vars: Vec<(String, String)>,
+        /// read values for template variables from environment variables of the same name
+        #[arg(long = "vars-from-env")]
+        vars_from_env: bool,
+    },
+    /// Unlock an encrypted stash for a while, so that commands don't ask for its passphrase
+    Unlock {
+        /// how long to keep the stash unlocked for, eg. 30s, 15m, 1h
+        #[arg(
+            short = 't',
+            long = "timeout",
+            value_name = "DURATION",
+            default_value = "15m",
+            value_parser = parse_duration
+        )]
+        timeout: Duration,
+    },
+    /// List template variables used in an entry
+    Vars {
+        /// key used when stashing content
+        #[arg(value_name = "KEY", add = ArgValueCandidates::new(complete_keys))]
+        key: String,
+    },
+    /// Print help for a command (eg. tash help sync init), or on a topic (keys, config, env, error-codes)
+    Help {
+        /// command, or topic, to print help for
+        #[arg(value_name = "COMMAND|TOPIC")]
+        path: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SyncCommand {
+    /// Turn the data directory into a git repository that syncs with a remote, and sync it
+    Init {
+        /// URL or path of the git remote to sync with (eg. a bare repository)
+        #[arg(value_name = "REMOTE")]
+        remote: String,
+    },
+}
+
+fn parse_template_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, v)) if !name.is_empty() => Ok((name.to_string(), v.to_string())),
+        _ => Err("needs to be of the form NAME=VALUE".to_string()),
+    }
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    const FORMAT_ERROR: &str = "needs to be of the form <NUMBER>[ms|s|m|h], eg. 30s";
+
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| FORMAT_ERROR.to_string())?;
+
+    let seconds = |multiplier: u64| {
+        number
+            .checked_mul(multiplier)
+            .map(Duration::from_secs)
+            .ok_or_else(|| "is too large".to_string())
+    };
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => seconds(60),
+        "h" => seconds(60 * 60),
+        _ => Err(FORMAT_ERROR.to_string()),
+    }
 }
@@ -0,0 +1,363 @@
+use arboard::{Error as ArboardError, ImageData};
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::io::Error as IOError;
+use std::path::PathBuf;
+
+/// Setting this makes tash use a file as its clipboard instead of the system clipboard. This is
+/// meant for testing clipboard related functionality on machines without a display server.
+const FAKE_CLIPBOARD_FILE_ENV_VAR: &str = "TASH_FAKE_CLIPBOARD_FILE";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClipboardError {
+    #[error("couldn't access system clipboard: {0}")]
+    Access(ArboardError),
+    #[error("couldn't read from system clipboard: {0}")]
+    Read(ArboardError),
+    #[error("couldn't write to system clipboard: {0}")]
+    Write(ArboardError),
+    #[error("couldn't encode image from system clipboard as PNG: {0}")]
+    EncodeImage(ImageError),
+    #[error("couldn't decode PNG content: {0}")]
+    DecodeImage(ImageError),
+    #[error("clipboard is empty")]
+    Empty,
+    #[error("couldn't read from clipboard file: {0}")]
+    ReadFile(IOError),
+    #[error("couldn't write to clipboard file: {0}")]
+    WriteFile(IOError),
+    #[error("clipboard file holds content that's neither text nor a PNG image")]
+    UnsupportedFileContent,
+    // only constructed on platforms other than Linux
+    #[allow(dead_code)]
+    #[error("the primary selection is only supported on Linux")]
+    PrimarySelectionUnsupported,
+}
+
+/// One of the clipboards tash can talk to. The primary selection (the text selected last) only
+/// exists on Linux (X11/Wayland).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardKind {
+    Clipboard,
+    Primary,
+}
+
+/// Which clipboard(s) to use. When reading from `Both`, the regular clipboard is preferred, and
+/// the primary selection is used as a fallback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    Primary,
+    Both,
+}
+
+impl Selection {
+    pub fn kinds(&self) -> &'static [ClipboardKind] {
+        match self {
+            Selection::Clipboard => &[ClipboardKind::Clipboard],
+            Selection::Primary => &[ClipboardKind::Primary],
+            Selection::Both => &[ClipboardKind::Clipboard, ClipboardKind::Primary],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClipboardContent {
+    Text(String),
+    /// PNG encoded image
+    Image(Vec<u8>),
+}
+
+impl ClipboardContent {
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            ClipboardContent::Text(t) => t.into_bytes(),
+            ClipboardContent::Image(i) => i,
+        }
+    }
+}
+
+pub trait Clipboard {
+    fn read_from(&mut self, kind: ClipboardKind) -> Result<ClipboardContent, ClipboardError>;
+    fn write_to(
+        &mut self,
+        kind: ClipboardKind,
+        content: &ClipboardContent,
+    ) -> Result<(), ClipboardError>;
+    fn clear(&mut self, kind: ClipboardKind) -> Result<(), ClipboardError>;
+
+    /// Reads from the first clipboard in the selection that holds content.
+    fn read(&mut self, selection: Selection) -> Result<ClipboardContent, ClipboardError> {
+        let mut first_error = None;
+        for kind in selection.kinds() {
+            match self.read_from(*kind) {
+                Ok(content) => return Ok(content),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or(ClipboardError::Empty))
+    }
+
+    fn write(
+        &mut self,
+        selection: Selection,
+        content: &ClipboardContent,
+    ) -> Result<(), ClipboardError> {
+        for kind in selection.kinds() {
+            self.write_to(*kind, content)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decides which clipboard implementation tash talks to. Connecting to the clipboard is deferred
+/// until it's actually needed, since that fails on machines without a display server.
+#[derive(Debug, Clone)]
+pub enum ClipboardProvider {
+    System,
+    File(PathBuf),
+}
+
+impl ClipboardProvider {
+    pub fn from_env() -> Self {
+        match std::env::var_os(FAKE_CLIPBOARD_FILE_ENV_VAR) {
+            Some(path) => ClipboardProvider::File(PathBuf::from(path)),
+            None => ClipboardProvider::System,
+        }
+    }
+
+    pub fn connect(&self) -> Result<Box<dyn Clipboard>, ClipboardError> {
+        match self {
+            ClipboardProvider::System => {
+                let clipboard = arboard::Clipboard::new().map_err(ClipboardError::Access)?;
+                Ok(Box::new(SystemClipboard(clipboard)))
+            }
+            ClipboardProvider::File(path) => Ok(Box::new(FileClipboard(path.clone()))),
+        }
+    }
+}
+
+struct SystemClipboard(arboard::Clipboard);
+
+impl Clipboard for SystemClipboard {
+    fn read_from(&mut self, kind: ClipboardKind) -> Result<ClipboardContent, ClipboardError> {
+        match platform::get_from(&mut self.0, kind)?.text() {
+            Ok(text) => Ok(ClipboardContent::Text(text)),
+            Err(ArboardError::ContentNotAvailable) => {
+                let image = platform::get_from(&mut self.0, kind)?
+                    .image()
+                    .map_err(ClipboardError::Read)?;
+                let png = image_to_png(&image).map_err(ClipboardError::EncodeImage)?;
+                Ok(ClipboardContent::Image(png))
+            }
+            Err(e) => Err(ClipboardError::Read(e)),
+        }
+    }
+
+    fn write_to(
+        &mut self,
+        kind: ClipboardKind,
+        content: &ClipboardContent,
+    ) -> Result<(), ClipboardError> {
+        match content {
+            ClipboardContent::Text(text) => {
+                platform::set_to(&mut self.0, kind)?
+                    .text(text)
+                    .map_err(ClipboardError::Write)?;
+            }
+            ClipboardContent::Image(png) => {
+                let image = png_to_image(png).map_err(ClipboardError::DecodeImage)?;
+                platform::set_to(&mut self.0, kind)?
+                    .image(image)
+                    .map_err(ClipboardError::Write)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, kind: ClipboardKind) -> Result<(), ClipboardError> {
+        platform::clear(&mut self.0, kind)
+    }
+}
+
+/// arboard only lets tash pick a clipboard via its Linux specific extensions; elsewhere, there's
+/// only the regular clipboard.
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+mod platform {
+    use super::{ClipboardError, ClipboardKind};
+    use arboard::{ClearExtLinux, GetExtLinux, LinuxClipboardKind, SetExtLinux};
+
+    fn linux_kind(kind: ClipboardKind) -> LinuxClipboardKind {
+        match kind {
+            ClipboardKind::Clipboard => LinuxClipboardKind::Clipboard,
+            ClipboardKind::Primary => LinuxClipboardKind::Primary,
+        }
+    }
+
+    pub(super) fn get_from(
+        clipboard: &mut arboard::Clipboard,
+        kind: ClipboardKind,
+    ) -> Result<arboard::Get<'_>, ClipboardError> {
+        Ok(clipboard.get().clipboard(linux_kind(kind)))
+    }
+
+    pub(super) fn set_to(
+        clipboard: &mut arboard::Clipboard,
+        kind: ClipboardKind,
+    ) -> Result<arboard::Set<'_>, ClipboardError> {
+        Ok(clipboard.set().clipboard(linux_kind(kind)))
+    }
+
+    pub(super) fn clear(
+        clipboard: &mut arboard::Clipboard,
+        kind: ClipboardKind,
+    ) -> Result<(), ClipboardError> {
+        clipboard
+            .clear_with()
+            .clipboard(linux_kind(kind))
+            .map_err(ClipboardError::Write)
+    }
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+)))]
+mod platform {
+    use super::{ClipboardError, ClipboardKind};
+
+    pub(super) fn get_from(
+        clipboard: &mut arboard::Clipboard,
+        kind: ClipboardKind,
+    ) -> Result<arboard::Get<'_>, ClipboardError> {
+        match kind {
+            ClipboardKind::Clipboard => Ok(clipboard.get()),
+            ClipboardKind::Primary => Err(ClipboardError::PrimarySelectionUnsupported),
+        }
+    }
+
+    pub(super) fn set_to(
+        clipboard: &mut arboard::Clipboard,
+        kind: ClipboardKind,
+    ) -> Result<arboard::Set<'_>, ClipboardError> {
+        match kind {
+            ClipboardKind::Clipboard => Ok(clipboard.set()),
+            ClipboardKind::Primary => Err(ClipboardError::PrimarySelectionUnsupported),
+        }
+    }
+
+    pub(super) fn clear(
+        clipboard: &mut arboard::Clipboard,
+        kind: ClipboardKind,
+    ) -> Result<(), ClipboardError> {
+        match kind {
+            ClipboardKind::Clipboard => clipboard.clear().map_err(ClipboardError::Write),
+            ClipboardKind::Primary => Err(ClipboardError::PrimarySelectionUnsupported),
+        }
+    }
+}
+
+/// Uses a file per clipboard; the primary selection lives next to the regular one, with a
+/// `.primary` suffix.
+struct FileClipboard(PathBuf);
+
+impl FileClipboard {
+    fn path(&self, kind: ClipboardKind) -> PathBuf {
+        match kind {
+            ClipboardKind::Clipboard => self.0.clone(),
+            ClipboardKind::Primary => {
+                let mut path = self.0.clone().into_os_string();
+                path.push(".primary");
+                PathBuf::from(path)
+            }
+        }
+    }
+}
+
+impl Clipboard for FileClipboard {
+    fn read_from(&mut self, kind: ClipboardKind) -> Result<ClipboardContent, ClipboardError> {
+        let bytes = match std::fs::read(self.path(kind)) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ClipboardError::Empty);
+            }
+            Err(e) => return Err(ClipboardError::ReadFile(e)),
+        };
+
+        if bytes.starts_with(PNG_SIGNATURE) {
+            return Ok(ClipboardContent::Image(bytes));
+        }
+
+        String::from_utf8(bytes)
+            .map(ClipboardContent::Text)
+            .map_err(|_| ClipboardError::UnsupportedFileContent)
+    }
+
+    fn write_to(
+        &mut self,
+        kind: ClipboardKind,
+        content: &ClipboardContent,
+    ) -> Result<(), ClipboardError> {
+        let bytes = match content {
+            ClipboardContent::Text(text) => text.as_bytes(),
+            ClipboardContent::Image(png) => {
+                // mirror the system clipboard, which only accepts valid images
+                png_to_image(png).map_err(ClipboardError::DecodeImage)?;
+                png.as_slice()
+            }
+        };
+
+        std::fs::write(self.path(kind), bytes).map_err(ClipboardError::WriteFile)
+    }
+
+    fn clear(&mut self, kind: ClipboardKind) -> Result<(), ClipboardError> {
+        match std::fs::remove_file(self.path(kind)) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(ClipboardError::WriteFile(e)),
+        }
+    }
+}
+
+/// Encodes an image read from the system clipboard (raw RGBA pixels) as PNG.
+fn image_to_png(image: &ImageData) -> Result<Vec<u8>, ImageError> {
+    let rgba = RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.to_vec(),
+    )
+    .ok_or(ImageError::Parameter(ParameterError::from_kind(
+        ParameterErrorKind::DimensionMismatch,
+    )))?;
+
+    let mut png = Vec::new();
+    rgba.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+/// Decodes PNG content into raw RGBA pixels that can be written to the system clipboard.
+fn png_to_image(png: &[u8]) -> Result<ImageData<'static>, ImageError> {
+    let rgba = image::load_from_memory_with_format(png, ImageFormat::Png)?.to_rgba8();
+
+    Ok(ImageData {
+        width: rgba.width() as usize,
+        height: rgba.height() as usize,
+        bytes: Cow::Owned(rgba.into_raw()),
+    })
+}
@@ -0,0 +1,49 @@
+use crate::actions::list_keys;
+use crate::handle::get_data_dir;
+use crate::vault::Vault;
+use clap::ValueEnum;
+use clap_complete::CompletionCandidate;
+use clap_complete::env::{Bash, EnvCompleter, Fish, Zsh};
+use std::io::Write;
+
+/// Environment variable that makes tash print completions (for the command line it's passed)
+/// instead of running a command.
+pub const COMPLETE_ENV_VAR: &str = "COMPLETE";
+const BIN: &str = "tash";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Writes a script that sets up completions for `shell`. Completions aren't baked into it; the
+/// shell asks tash for them (via `COMPLETE_ENV_VAR`), so that they include the keys in the stash
+/// at that point.
+pub fn write_completions(shell: Shell, buf: &mut dyn Write) -> Result<(), std::io::Error> {
+    let completer: &dyn EnvCompleter = match shell {
+        Shell::Bash => &Bash,
+        Shell::Zsh => &Zsh,
+        Shell::Fish => &Fish,
+    };
+
+    completer.write_registration(COMPLETE_ENV_VAR, BIN, BIN, BIN, buf)
+}
+
+/// Returns the keys in the stash, for completing arguments that refer to entries. Keys of an
+/// encrypted stash aren't offered, since listing them would need its passphrase.
+pub fn complete_keys() -> Vec<CompletionCandidate> {
+    let Ok(data_dir) = get_data_dir() else {
+        return Vec::new();
+    };
+    if Vault::new(&data_dir).is_ok_and(|v| v.is_encrypted()) {
+        return Vec::new();
+    }
+
+    list_keys(&data_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
@@ -0,0 +1,75 @@
+use crate::clipboard::Selection;
+use dirs::config_dir;
+use serde::Deserialize;
+use std::io::Error as IOError;
+use std::path::PathBuf;
+
+const CONFIG_DIR: &str = "tash";
+const CONFIG_FILE: &str = "tash.toml";
+const CONFIG_PATH_ENV_VAR: &str = "TASH_CONFIG_PATH";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("TASH_CONFIG_PATH value is invalid: {0}")]
+    ConfigPathEnvVarInvalid(std::env::VarError),
+    #[error("couldn't read config file {0}: {1}")]
+    ReadFile(PathBuf, IOError),
+    #[error("couldn't parse config file {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+}
+
+/// tash's configuration, read from `<CONFIG_DIR>/tash/tash.toml` (or the path in
+/// `TASH_CONFIG_PATH`). Everything in it is optional; command line flags take precedence over
+/// it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// default clipboard selection to use for "push -c" and "get -c"
+    pub selection: Option<Selection>,
+    /// age identity file to use for encrypting/decrypting content
+    pub identity_file: Option<PathBuf>,
+    /// patterns for secrets "push" should look out for, in addition to the built-in ones
+    #[serde(default)]
+    pub secret_patterns: Vec<SecretPattern>,
+    /// whether to compress content (via zstd) before storing it, as if "push --compress" was used
+    #[serde(default)]
+    pub compress: bool,
+    /// whether to store identical content only once (entries then refer to shared blobs)
+    #[serde(default)]
+    pub dedupe: bool,
+    /// whether to leave changes uncommitted until "tash sync" runs (instead of committing them
+    /// after every command that makes them)
+    #[serde(default)]
+    pub sync_batch_commits: bool,
+    /// token clients of "tash serve" need to send as a bearer token (the server refuses to start
+    /// without one)
+    pub serve_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretPattern {
+    /// what to call the secret in warnings
+    pub name: String,
+    /// regex to match the secret with
+    pub pattern: String,
+}
+
+pub fn load_config() -> Result<Config, ConfigError> {
+    let config_path = match std::env::var(CONFIG_PATH_ENV_VAR) {
+        Ok(path) => PathBuf::from(path),
+        Err(std::env::VarError::NotPresent) => match config_dir() {
+            Some(dir) => dir.join(CONFIG_DIR).join(CONFIG_FILE),
+            None => return Ok(Config::default()),
+        },
+        Err(e) => return Err(ConfigError::ConfigPathEnvVarInvalid(e)),
+    };
+
+    let contents = match std::fs::read_to_string(&config_path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(ConfigError::ReadFile(config_path, e)),
+    };
+
+    toml::from_str(&contents).map_err(|e| ConfigError::Parse(config_path, e))
+}
@@ -0,0 +1,234 @@
+use age::secrecy::SecretString;
+use age::{DecryptError, Decryptor, EncryptError, Encryptor, IdentityFile, x25519};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::io::Error as IOError;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+const PASSPHRASE_ENV_VAR: &str = "TASH_PASSPHRASE";
+const IDENTITY_FILE_ENV_VAR: &str = "TASH_IDENTITY_FILE";
+/// Content encrypted with age starts with this.
+pub const AGE_HEADER: &[u8] = b"age-encryption.org/v1";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CryptoError {
+    #[error("TASH_PASSPHRASE value is invalid: {0}")]
+    PassphraseEnvVarInvalid(std::env::VarError),
+    #[error("couldn't read passphrase from the terminal (TASH_PASSPHRASE can be set instead): {0}")]
+    ReadPassphrase(IOError),
+    #[error("can't prompt for a passphrase here; set TASH_PASSPHRASE instead")]
+    PromptNotAllowed,
+    #[error("passphrase is empty")]
+    EmptyPassphrase,
+    #[error("passphrases don't match")]
+    PassphraseMismatch,
+    #[error(
+        "no identity file configured (set TASH_IDENTITY_FILE, or \"identity_file\" in the config)"
+    )]
+    NoIdentityFile,
+    #[error("couldn't read identity file {0}: {1}")]
+    ReadIdentityFile(PathBuf, IOError),
+    #[error("identity file {0} is invalid: {1}")]
+    InvalidIdentityFile(PathBuf, String),
+    #[error("couldn't encrypt content: {0}")]
+    Encrypt(EncryptError),
+    #[error("couldn't encrypt content: {0}")]
+    WriteEncrypted(IOError),
+    #[error("couldn't decrypt content: {0}")]
+    Decrypt(DecryptError),
+    #[error("couldn't decrypt content: {0}")]
+    ReadDecrypted(IOError),
+}
+
+/// How an entry's content was encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionScheme {
+    /// age, with a passphrase
+    Passphrase,
+    /// age, with the recipients of an identity file
+    Identity,
+}
+
+/// Knows where to get the secrets needed for encrypting and decrypting content from.
+///
+/// An identity file (from `TASH_IDENTITY_FILE`, or the config) takes precedence over a
+/// passphrase. The passphrase is read from `TASH_PASSPHRASE`, or prompted for on the terminal,
+/// at most once per invocation (unless prompts are turned off).
+#[derive(Debug)]
+pub struct KeySource {
+    identity_file: Option<PathBuf>,
+    passphrase: OnceCell<SecretString>,
+    allow_prompt: bool,
+}
+
+impl KeySource {
+    pub fn new(identity_file_from_config: Option<PathBuf>) -> Self {
+        let identity_file = std::env::var_os(IDENTITY_FILE_ENV_VAR)
+            .map(PathBuf::from)
+            .or(identity_file_from_config);
+
+        Self {
+            identity_file,
+            passphrase: OnceCell::new(),
+            allow_prompt: true,
+        }
+    }
+
+    /// Makes needing a passphrase that isn't in `TASH_PASSPHRASE` an error, instead of a prompt;
+    /// meant for long running commands, which can't wait on the terminal.
+    pub fn without_prompts(self) -> Self {
+        Self {
+            allow_prompt: false,
+            ..self
+        }
+    }
+
+    /// The scheme used for newly encrypted content.
+    pub fn default_scheme(&self) -> EncryptionScheme {
+        match self.identity_file {
+            Some(_) => EncryptionScheme::Identity,
+            None => EncryptionScheme::Passphrase,
+        }
+    }
+
+    pub fn encrypt(
+        &self,
+        scheme: EncryptionScheme,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let encryptor = match scheme {
+            EncryptionScheme::Passphrase => {
+                Encryptor::with_user_passphrase(self.passphrase(true)?.clone())
+            }
+            EncryptionScheme::Identity => {
+                let recipients = self
+                    .identity_file()?
+                    .to_recipients()
+                    .map_err(CryptoError::Encrypt)?;
+                Encryptor::with_recipients(recipients.iter().map(|r| r.as_ref() as _))
+                    .map_err(CryptoError::Encrypt)?
+            }
+        };
+
+        encrypt_with(encryptor, plaintext)
+    }
+
+    pub fn decrypt(
+        &self,
+        scheme: EncryptionScheme,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        match scheme {
+            EncryptionScheme::Passphrase => {
+                let identity = age::scrypt::Identity::new(self.passphrase(false)?.clone());
+                decrypt_with(std::iter::once(&identity as &dyn age::Identity), ciphertext)
+            }
+            EncryptionScheme::Identity => {
+                let identities = self
+                    .identity_file()?
+                    .into_identities()
+                    .map_err(CryptoError::Decrypt)?;
+                decrypt_with(identities.iter().map(|i| i.as_ref()), ciphertext)
+            }
+        }
+    }
+
+    fn identity_file(&self) -> Result<IdentityFile<age::NoCallbacks>, CryptoError> {
+        let path = self
+            .identity_file
+            .as_ref()
+            .ok_or(CryptoError::NoIdentityFile)?;
+
+        let contents =
+            std::fs::read(path).map_err(|e| CryptoError::ReadIdentityFile(path.clone(), e))?;
+
+        IdentityFile::from_buffer(contents.as_slice())
+            .map_err(|e| CryptoError::InvalidIdentityFile(path.clone(), e.to_string()))
+    }
+
+    /// Passphrases for new content are asked for twice, to guard against typos.
+    fn passphrase(&self, confirm: bool) -> Result<&SecretString, CryptoError> {
+        if let Some(passphrase) = self.passphrase.get() {
+            return Ok(passphrase);
+        }
+
+        let passphrase = match std::env::var(PASSPHRASE_ENV_VAR) {
+            Ok(p) => p,
+            Err(std::env::VarError::NotPresent) if !self.allow_prompt => {
+                return Err(CryptoError::PromptNotAllowed);
+            }
+            Err(std::env::VarError::NotPresent) => {
+                let passphrase = rpassword::prompt_password("Passphrase: ")
+                    .map_err(CryptoError::ReadPassphrase)?;
+                if confirm {
+                    let confirmation = rpassword::prompt_password("Confirm passphrase: ")
+                        .map_err(CryptoError::ReadPassphrase)?;
+                    if confirmation != passphrase {
+                        return Err(CryptoError::PassphraseMismatch);
+                    }
+                }
+                passphrase
+            }
+            Err(e) => return Err(CryptoError::PassphraseEnvVarInvalid(e)),
+        };
+
+        if passphrase.is_empty() {
+            return Err(CryptoError::EmptyPassphrase);
+        }
+
+        Ok(self
+            .passphrase
+            .get_or_init(|| SecretString::from(passphrase)))
+    }
+}
+
+/// Encrypts content for an x25519 identity's recipient (used for keys tash generates itself).
+pub fn encrypt_for_identity(
+    identity: &x25519::Identity,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let recipient = identity.to_public();
+    let encryptor = Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient))
+        .map_err(CryptoError::Encrypt)?;
+
+    encrypt_with(encryptor, plaintext)
+}
+
+pub fn decrypt_with_identity(
+    identity: &x25519::Identity,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    decrypt_with(std::iter::once(identity as &dyn age::Identity), ciphertext)
+}
+
+fn encrypt_with(encryptor: Encryptor, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut ciphertext = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut ciphertext)
+        .map_err(CryptoError::WriteEncrypted)?;
+    writer
+        .write_all(plaintext)
+        .map_err(CryptoError::WriteEncrypted)?;
+    writer.finish().map_err(CryptoError::WriteEncrypted)?;
+
+    Ok(ciphertext)
+}
+
+fn decrypt_with<'a>(
+    identities: impl Iterator<Item = &'a dyn age::Identity>,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let decryptor = Decryptor::new(ciphertext).map_err(CryptoError::Decrypt)?;
+    let mut reader = decryptor
+        .decrypt(identities)
+        .map_err(CryptoError::Decrypt)?;
+
+    let mut plaintext = Vec::new();
+    reader
+        .read_to_end(&mut plaintext)
+        .map_err(CryptoError::ReadDecrypted)?;
+
+    Ok(plaintext)
+}
@@ -0,0 +1,249 @@
+use crate::actions::{
+    ContentType, EntryDetails, ListError, StashedContent, list_entries, read_content,
+    stash_modified_at,
+};
+use crate::args::Action;
+use crate::crypto::KeySource;
+use crate::permissions::PRIVATE_FILE_MODE;
+use crate::vault::{Vault, VaultError};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use std::fs::Permissions;
+use std::io::Error as IOError;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// How long either side waits on the other before giving up on a request.
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// Changes made within this long of the stash being indexed may share a modification time with
+/// it, so such indexes aren't trusted to be current.
+const MODIFICATION_TIME_GRANULARITY: Duration = Duration::from_secs(1);
+
+#[derive(thiserror::Error, Debug)]
+pub enum DaemonError {
+    #[error("encrypted stashes can't be served by the daemon")]
+    EncryptedStash,
+    #[error("a daemon is already running for this stash (listening on {0})")]
+    AlreadyRunning(String),
+    #[error(transparent)]
+    CreateRuntimeDir(VaultError),
+    #[error("couldn't remove stale socket: {0}")]
+    RemoveStaleSocket(IOError),
+    #[error("couldn't listen on {0}: {1}")]
+    Listen(String, IOError),
+    #[error("couldn't index the stash: {0}")]
+    IndexStash(ListError),
+}
+
+/// Requests the CLI sends to the daemon, one JSON object per line.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "kebab-case")]
+enum DaemonRequest {
+    List,
+    Get { key: String },
+}
+
+/// The daemon only answers requests it can handle completely; everything else (including ones
+/// that would fail) is left to the CLI, so that errors are reported the same way either way.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "kebab-case")]
+pub enum DaemonResponse {
+    Entries {
+        entries: Vec<EntryDetails>,
+    },
+    Content {
+        content_base64: String,
+        content_type: ContentType,
+        sensitive: bool,
+    },
+    Fallback,
+}
+
+impl DaemonResponse {
+    pub fn into_entries(self) -> Option<Vec<EntryDetails>> {
+        match self {
+            DaemonResponse::Entries { entries } => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn into_content(self) -> Option<StashedContent> {
+        match self {
+            DaemonResponse::Content {
+                content_base64,
+                content_type,
+                sensitive,
+            } => Some(StashedContent {
+                contents: BASE64.decode(content_base64).ok()?,
+                content_type,
+                sensitive,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Details of every entry, kept in memory so that listing entries doesn't need the data directory
+/// to be scanned every time.
+struct Index {
+    entries: Vec<EntryDetails>,
+    modified_at: [Option<SystemTime>; 2],
+    indexed_at: SystemTime,
+}
+
+impl Index {
+    fn build(data_dir: &Path) -> Result<Self, ListError> {
+        let modified_at = stash_modified_at(data_dir);
+        let indexed_at = SystemTime::now();
+        let entries = list_entries(data_dir, true)?;
+
+        Ok(Self {
+            entries,
+            modified_at,
+            indexed_at,
+        })
+    }
+
+    fn is_current(&self, data_dir: &Path) -> bool {
+        let modified_at = stash_modified_at(data_dir);
+        let is_racy = self.modified_at.iter().flatten().any(|m| {
+            !self
+                .indexed_at
+                .duration_since(*m)
+                .is_ok_and(|d| d >= MODIFICATION_TIME_GRANULARITY)
+        });
+
+        modified_at == self.modified_at && !is_racy
+    }
+}
+
+/// Serves "ls" and "get" for the stash in `data_dir` over a Unix socket in the runtime directory,
+/// until the process is stopped. Entries are listed from an in-memory index, which is rebuilt
+/// whenever the stash changes.
+pub fn run_daemon(
+    data_dir: &Path,
+    vault: &Vault,
+    key_source: &KeySource,
+) -> Result<(), DaemonError> {
+    if vault.is_encrypted() {
+        return Err(DaemonError::EncryptedStash);
+    }
+
+    let socket = vault.daemon_socket();
+    let socket_str = socket.to_string_lossy().to_string();
+    vault
+        .create_runtime_dir()
+        .map_err(DaemonError::CreateRuntimeDir)?;
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(DaemonError::AlreadyRunning(socket_str));
+        }
+        // left behind by a daemon that didn't get to clean up
+        std::fs::remove_file(socket).map_err(DaemonError::RemoveStaleSocket)?;
+    }
+
+    let listener =
+        UnixListener::bind(socket).map_err(|e| DaemonError::Listen(socket_str.clone(), e))?;
+    std::fs::set_permissions(socket, Permissions::from_mode(PRIVATE_FILE_MODE))
+        .map_err(|e| DaemonError::Listen(socket_str.clone(), e))?;
+
+    let mut index = Index::build(data_dir).map_err(DaemonError::IndexStash)?;
+    println!("listening on {socket_str}");
+
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| {
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            respond(stream, data_dir, &mut index, key_source)
+        });
+        if let Err(e) = result {
+            eprintln!("couldn't handle request: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+fn respond(
+    stream: UnixStream,
+    data_dir: &Path,
+    index: &mut Index,
+    key_source: &KeySource,
+) -> Result<(), IOError> {
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    // connections that close without a request (eg. ones checking whether a daemon is running)
+    // don't need a response
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(());
+    }
+
+    if !index.is_current(data_dir) {
+        match Index::build(data_dir) {
+            Ok(new_index) => *index = new_index,
+            Err(e) => eprintln!("couldn't index the stash: {e}"),
+        }
+    }
+
+    let response = match serde_json::from_str(&line) {
+        Ok(DaemonRequest::List) => DaemonResponse::Entries {
+            entries: index.entries.clone(),
+        },
+        Ok(DaemonRequest::Get { key }) => {
+            let entry = index.entries.iter().find(|e| e.key == key);
+            // encrypted entries may need a passphrase, which only the CLI can ask for
+            match entry.filter(|e| !e.encrypted) {
+                Some(_) => match read_content(data_dir, &key, key_source) {
+                    Ok(content) => DaemonResponse::Content {
+                        content_base64: BASE64.encode(&content.contents),
+                        content_type: content.content_type,
+                        sensitive: content.sensitive,
+                    },
+                    Err(_) => DaemonResponse::Fallback,
+                },
+                None => DaemonResponse::Fallback,
+            }
+        }
+        Err(_) => DaemonResponse::Fallback,
+    };
+
+    let mut response = serde_json::to_vec(&response).map_err(IOError::other)?;
+    response.push(b'\n');
+    (&stream).write_all(&response)
+}
+
+/// Asks the daemon for the stash to handle an action, if one's running and the action is one it
+/// serves. Returns `None` if the CLI needs to handle the action itself.
+pub fn ask_daemon(vault: &Vault, action: &Action) -> Option<DaemonResponse> {
+    let request = match action {
+        Action::LS { .. } => DaemonRequest::List,
+        // popping changes the stash, which is left to the CLI
+        Action::Get {
+            key, pop: false, ..
+        } => DaemonRequest::Get { key: key.clone() },
+        _ => return None,
+    };
+    if vault.is_encrypted() {
+        return None;
+    }
+
+    let mut stream = UnixStream::connect(vault.daemon_socket()).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+
+    let mut message = serde_json::to_vec(&request).ok()?;
+    message.push(b'\n');
+    stream.write_all(&message).ok()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+
+    match serde_json::from_str(&line).ok()? {
+        DaemonResponse::Fallback => None,
+        response => Some(response),
+    }
+}
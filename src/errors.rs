@@ -1,4 +1,17 @@
-use crate::actions::{DeleteError, EmptyError, GetError, ListError, PushError};
+use crate::actions::{
+    AppendError, ClearClipboardError, DeleteError, DoctorError, EmptyError, ExportError,
+    FindDupesError, GcError, GetError, ImportError, InputError, ListError, PushError, RunError,
+    SearchError, SyncDirError, VarsError,
+};
+use crate::clipboard::ClipboardError;
+use crate::config::ConfigError;
+use crate::crypto::CryptoError;
+use crate::daemon::DaemonError;
+use crate::help::{HelpError, ManError};
+use crate::mcp::McpError;
+use crate::serve::ServeError;
+use crate::sync::SyncError;
+use crate::vault::VaultError;
 use std::io::Error as IOError;
 
 #[derive(thiserror::Error, Debug)]
@@ -9,6 +22,8 @@ pub enum AppError {
     DataDirEnvVarInvalid(std::env::VarError),
     #[error("couldn't create data directory: {0}")]
     CreateDataDir(IOError),
+    #[error("couldn't load config: {0}")]
+    LoadConfig(ConfigError),
     #[error("couldn't empty stash: {0}")]
     EmptyStash(EmptyError),
     #[error("couldn't get content: {0}")]
@@ -19,6 +34,60 @@ pub enum AppError {
     PushContent(PushError),
     #[error("couldn't delete content: {0}")]
     DeleteContent(DeleteError),
+    #[error("couldn't append content: {0}")]
+    AppendContent(AppendError),
+    #[error("couldn't run content: {0}")]
+    RunContent(RunError),
+    #[error("couldn't clear clipboard: {0}")]
+    ClearClipboard(ClearClipboardError),
+    #[error("couldn't list template variables: {0}")]
+    ListTemplateVars(VarsError),
+    #[error("couldn't export content: {0}")]
+    ExportContent(ExportError),
+    #[error("couldn't import content: {0}")]
+    ImportContent(ImportError),
+    #[error("couldn't find duplicates: {0}")]
+    FindDuplicates(FindDupesError),
+    #[error("couldn't collect garbage: {0}")]
+    CollectGarbage(GcError),
+    #[error("couldn't check stash: {0}")]
+    RunDoctor(DoctorError),
+    #[error("{0}")]
+    AccessVault(VaultError),
+    #[error("couldn't encrypt stash: {0}")]
+    EncryptStash(VaultError),
+    #[error("couldn't decrypt stash: {0}")]
+    DecryptStash(VaultError),
+    #[error("couldn't unlock stash: {0}")]
+    UnlockStash(VaultError),
+    #[error("couldn't lock stash: {0}")]
+    LockStash(VaultError),
+    #[error("couldn't open stash: {0}")]
+    OpenStash(VaultError),
+    #[error("couldn't save stash: {0}")]
+    SaveStash(VaultError),
+    #[error("couldn't set up sync: {0}")]
+    SetUpSync(SyncError),
+    #[error("couldn't sync stash: {0}")]
+    SyncStash(SyncError),
+    #[error("couldn't commit changes for sync: {0}")]
+    CommitForSync(SyncError),
+    #[error("couldn't sync stash with directory: {0}")]
+    SyncWithDir(SyncDirError),
+    #[error("couldn't search content: {0}")]
+    SearchEntries(SearchError),
+    #[error("couldn't serve API: {0}")]
+    Serve(ServeError),
+    #[error("couldn't run daemon: {0}")]
+    RunDaemon(DaemonError),
+    #[error("couldn't run MCP server: {0}")]
+    RunMcpServer(McpError),
+    #[error("couldn't write completions: {0}")]
+    WriteCompletions(std::io::Error),
+    #[error("{0}")]
+    ShowHelp(HelpError),
+    #[error("couldn't write man pages: {0}")]
+    WriteManPages(ManError),
 }
 
 impl AppError {
@@ -27,41 +96,281 @@ impl AppError {
             AppError::GetDataDir => None,
             AppError::DataDirEnvVarInvalid(_) => None,
             AppError::CreateDataDir(_) => Some(101),
+            AppError::LoadConfig(e) => match e {
+                ConfigError::ConfigPathEnvVarInvalid(_) => None,
+                ConfigError::ReadFile(_, _) => Some(102),
+                ConfigError::Parse(_, _) => None,
+            },
             AppError::EmptyStash(e) => match e {
                 EmptyError::ReadFromDataDir(_) => Some(200),
                 EmptyError::ReadFromStdin(_) => Some(201),
                 EmptyError::DeleteDataDir(_) => Some(203),
             },
-            AppError::GetContent(e) => match e {
-                GetError::KeyDoesntExist => None,
-                GetError::CouldntRemoveFile(_) => Some(300),
-                GetError::CouldntOpenFile(_) => Some(301),
-                GetError::CouldntReadFile(_) => Some(302),
-                GetError::CouldntAccessSystemClipboard(_) => Some(303),
-                GetError::CouldntWriteToSystemClipboard(_) => Some(304),
-            },
-            AppError::ListContent(e) => match e {
-                ListError::ReadFilesInDataDir(_) => Some(400),
-                ListError::GetFileFromDataDir(_) => Some(401),
-                ListError::GetFileStem(_) => Some(402),
-            },
+            AppError::GetContent(e) => get_error_code(e),
+            AppError::ListContent(e) => list_error_code(e),
             AppError::PushContent(e) => match e {
                 PushError::IncorrectKeyProvided => None,
                 PushError::KeyAlreadyExists => None,
-                PushError::MultipleInputSourcesProvided => None,
-                PushError::CouldntOpenFile(_) => Some(500),
-                PushError::CouldntReadFile(_) => Some(501),
-                PushError::CouldntReadFromStdin(_) => Some(502),
-                PushError::CouldntAccessSystemClipboard(_) => Some(503),
-                PushError::CouldntReadFromSystemClipboard(_) => Some(504),
+                PushError::CouldntReadInput(e) => match e {
+                    InputError::MultipleInputSourcesProvided => None,
+                    InputError::CouldntOpenFile(_) => Some(500),
+                    InputError::CouldntReadFile(_) => Some(501),
+                    InputError::CouldntReadFromStdin(_) => Some(502),
+                    InputError::CouldntReadFromClipboard(e) => clipboard_error_code(e),
+                },
                 PushError::ContentTooLarge(_) => None,
+                PushError::InvalidSecretPattern(_, _) => None,
+                PushError::SecretsDetected(_) => None,
                 PushError::CouldntWriteToFile(_) => Some(505),
+                PushError::CouldntEncryptContent(e) => crypto_error_code(e),
+                PushError::CouldntWriteMetadata(_) => Some(507),
                 PushError::CouldntEchoContent(_) => Some(506),
+                PushError::CouldntCompressContent(_) => Some(508),
             },
             AppError::DeleteContent(e) => match e {
                 DeleteError::KeysDontExist(_) => None,
                 DeleteError::CouldntRemoveFiles(_, _) => Some(600),
             },
+            AppError::AppendContent(e) => match e {
+                AppendError::IncorrectKeyProvided => None,
+                AppendError::CouldntReadInput(e) => match e {
+                    InputError::MultipleInputSourcesProvided => None,
+                    InputError::CouldntOpenFile(_) => Some(700),
+                    InputError::CouldntReadFile(_) => Some(701),
+                    InputError::CouldntReadFromStdin(_) => Some(702),
+                    InputError::CouldntReadFromClipboard(e) => clipboard_error_code(e),
+                },
+                AppendError::CouldntReadExistingContent(e) => get_error_code(e),
+                AppendError::ContentTooLarge(_) => None,
+                AppendError::InvalidSecretPattern(_, _) => None,
+                AppendError::SecretsDetected(_) => None,
+                AppendError::CouldntWriteToFile(_) => Some(707),
+                AppendError::CouldntReadMetadata(_) => Some(708),
+                AppendError::CouldntWriteMetadata(_) => Some(709),
+                AppendError::CouldntEncryptContent(e) => crypto_error_code(e),
+                AppendError::CouldntCompressContent(_) => Some(711),
+            },
+            AppError::RunContent(e) => match e {
+                RunError::CouldntReadContent(e) => get_error_code(e),
+                RunError::MissingTemplateVars(_) => None,
+                RunError::ShellEnvVarInvalid(_) => None,
+                RunError::ReadFromStdin(_) => Some(901),
+                RunError::CouldntRunCommand(_, _) => None,
+                RunError::ContentNotText(_) => None,
+            },
+            AppError::ClearClipboard(e) => match e {
+                ClearClipboardError::ReadRequest(_) => Some(1100),
+                ClearClipboardError::ParseRequest(_) => Some(1101),
+                ClearClipboardError::Clipboard(e) => clipboard_error_code(e),
+            },
+            AppError::ListTemplateVars(e) => match e {
+                VarsError::CouldntReadContent(e) => get_error_code(e),
+                VarsError::ContentNotText(_) => None,
+            },
+            AppError::ExportContent(e) => match e {
+                ExportError::KeysDontExist(_) => None,
+                ExportError::NothingToExport => None,
+                ExportError::ReadFilesInDataDir(_) => Some(1700),
+                ExportError::ReadContent(_, _) => Some(1701),
+                ExportError::ReadMetadata(_, _) => Some(1702),
+                ExportError::CreateBundle(_) => Some(1703),
+                ExportError::EncryptExport(e) => crypto_error_code(e),
+                ExportError::WriteOutput(_) => Some(1704),
+            },
+            AppError::ImportContent(e) => match e {
+                ImportError::ReadSource(_) => None,
+                ImportError::ReadDir(_) => None,
+                ImportError::ReadFile(_, _) => Some(1800),
+                ImportError::ContentTooLarge(_, _) => None,
+                ImportError::DecryptSource(e) => crypto_error_code(e),
+                ImportError::ReadBundle(_) => None,
+                ImportError::ReadFilesInDataDir(_) => Some(1801),
+                ImportError::KeysAlreadyExist(_) => None,
+                ImportError::ReadFromStdin(_) => Some(1802),
+                ImportError::CompressContent(_, _) => Some(1803),
+                ImportError::WriteContent(_, _) => Some(1804),
+                ImportError::WriteMetadata(_, _) => Some(1805),
+                ImportError::RemoveMetadata(_, _) => Some(1806),
+            },
+            AppError::FindDuplicates(e) => match e {
+                FindDupesError::ReadFilesInDataDir(_) => Some(1500),
+                FindDupesError::ReadMetadata(_, _) => Some(1501),
+                FindDupesError::ComputeChecksum(_, _) => Some(1502),
+            },
+            AppError::CollectGarbage(e) => match e {
+                GcError::ReadDataDir(_) => Some(1600),
+                GcError::ReadBlobsDir(_) => Some(1601),
+                GcError::RemoveBlob(_, _) => Some(1602),
+            },
+            AppError::RunDoctor(e) => match e {
+                DoctorError::ReadDataDir(_) => Some(1400),
+                DoctorError::GetFileDetails(_, _) => Some(1401),
+                DoctorError::SetPermissions(_, _) => Some(1402),
+                DoctorError::ReadFile(_, _) => Some(1403),
+                DoctorError::RemoveFile(_, _) => Some(1404),
+                DoctorError::WriteMetadata(_, _) => Some(1405),
+            },
+            AppError::AccessVault(e) => vault_error_code(e),
+            AppError::EncryptStash(e) => vault_error_code(e),
+            AppError::DecryptStash(e) => vault_error_code(e),
+            AppError::UnlockStash(e) => vault_error_code(e),
+            AppError::LockStash(e) => vault_error_code(e),
+            AppError::OpenStash(e) => vault_error_code(e),
+            AppError::SaveStash(e) => vault_error_code(e),
+            AppError::SetUpSync(e) => sync_error_code(e),
+            AppError::SyncStash(e) => sync_error_code(e),
+            AppError::CommitForSync(e) => sync_error_code(e),
+            AppError::SyncWithDir(e) => match e {
+                SyncDirError::CreateDir(_) => None,
+                SyncDirError::ResolveDir(_) => None,
+                SyncDirError::SameDir => None,
+                SyncDirError::EncryptedDir => None,
+                SyncDirError::EncryptedStash => None,
+                SyncDirError::ReadState(_) => Some(2000),
+                SyncDirError::ParseState(_) => None,
+                SyncDirError::SerializeState(_) => Some(2001),
+                SyncDirError::WriteState(_) => Some(2002),
+                SyncDirError::ListEntries(_, _) => None,
+                SyncDirError::ReadContent(_, _) => Some(2003),
+                SyncDirError::ReadMetadata(_, _) => None,
+                SyncDirError::WriteContent(_, _) => Some(2004),
+                SyncDirError::WriteMetadata(_, _) => Some(2005),
+                SyncDirError::WriteConflict(_, _) => Some(2006),
+                SyncDirError::RemoveEntry(_, _) => Some(2007),
+            },
+            AppError::SearchEntries(e) => match e {
+                SearchError::ListEntries(e) => list_error_code(e),
+                SearchError::ReadContent(_, e) => get_error_code(e),
+            },
+            AppError::Serve(e) => match e {
+                ServeError::NoToken => None,
+                ServeError::Bind(_, _) => None,
+            },
+            AppError::RunDaemon(e) => match e {
+                DaemonError::EncryptedStash => None,
+                DaemonError::AlreadyRunning(_) => None,
+                DaemonError::CreateRuntimeDir(e) => vault_error_code(e),
+                DaemonError::RemoveStaleSocket(_) => Some(2100),
+                DaemonError::Listen(_, _) => None,
+                DaemonError::IndexStash(e) => list_error_code(e),
+            },
+            AppError::RunMcpServer(e) => match e {
+                McpError::ReadMessage(_) => Some(2200),
+                McpError::WriteMessage(_) => Some(2201),
+            },
+            AppError::WriteCompletions(_) => Some(2300),
+            AppError::ShowHelp(e) => match e {
+                HelpError::UnknownTopic(_) => None,
+                HelpError::WriteHelp(_) => Some(2400),
+            },
+            AppError::WriteManPages(e) => match e {
+                ManError::CreateDir(_, _) => Some(2401),
+                ManError::WritePage(_, _) => Some(2402),
+            },
         }
     }
 }
+
+fn get_error_code(error: &GetError) -> Option<u16> {
+    match error {
+        GetError::KeyDoesntExist => None,
+        GetError::CouldntRemoveFile(_) => Some(300),
+        GetError::CouldntOpenFile(_) => Some(301),
+        GetError::CouldntReadFile(_) => Some(302),
+        GetError::CouldntWriteToClipboard(e) => clipboard_error_code(e),
+        GetError::MissingTemplateVars(_) => None,
+        GetError::CouldntReadMetadata(_) => Some(305),
+        GetError::ContentNotText(_) => None,
+        GetError::CouldntPrettyPrintJson(_) => None,
+        GetError::BinaryContentToTerminal(_) => None,
+        GetError::CouldntWriteToStdout(_) => Some(306),
+        GetError::UnsupportedClipboardContent(_) => None,
+        GetError::CouldntScheduleClipboardClear(_) => Some(307),
+        GetError::CouldntDecryptContent(e) => crypto_error_code(e),
+        GetError::ChecksumMismatch => None,
+        GetError::CouldntDecompressContent(_) => Some(308),
+    }
+}
+
+fn list_error_code(error: &ListError) -> Option<u16> {
+    match error {
+        ListError::ReadFilesInDataDir(_) => Some(400),
+        ListError::GetFileDetails(_) => Some(403),
+        ListError::ReadMetadata(_, _) => Some(404),
+        ListError::ComputeChecksum(_, _) => Some(405),
+    }
+}
+
+fn clipboard_error_code(error: &ClipboardError) -> Option<u16> {
+    match error {
+        ClipboardError::Access(_) => Some(1000),
+        ClipboardError::Read(_) => Some(1001),
+        ClipboardError::Write(_) => Some(1002),
+        ClipboardError::EncodeImage(_) => Some(1003),
+        ClipboardError::DecodeImage(_) => None,
+        ClipboardError::Empty => None,
+        ClipboardError::ReadFile(_) => Some(1004),
+        ClipboardError::WriteFile(_) => Some(1005),
+        ClipboardError::UnsupportedFileContent => None,
+        ClipboardError::PrimarySelectionUnsupported => None,
+    }
+}
+
+fn crypto_error_code(error: &CryptoError) -> Option<u16> {
+    match error {
+        CryptoError::PassphraseEnvVarInvalid(_) => None,
+        CryptoError::ReadPassphrase(_) => None,
+        CryptoError::PromptNotAllowed => None,
+        CryptoError::EmptyPassphrase => None,
+        CryptoError::PassphraseMismatch => None,
+        CryptoError::NoIdentityFile => None,
+        CryptoError::ReadIdentityFile(_, _) => None,
+        CryptoError::InvalidIdentityFile(_, _) => None,
+        CryptoError::Encrypt(_) => Some(1200),
+        CryptoError::WriteEncrypted(_) => Some(1201),
+        CryptoError::Decrypt(_) => None,
+        CryptoError::ReadDecrypted(_) => Some(1202),
+    }
+}
+
+fn vault_error_code(error: &VaultError) -> Option<u16> {
+    match error {
+        VaultError::RuntimeDirEnvVarInvalid(_) => None,
+        VaultError::CreateRuntimeDir(_) => Some(1300),
+        VaultError::RuntimeDirNotPrivate(_) => None,
+        VaultError::Lock(_) => Some(1313),
+        VaultError::AlreadyEncrypted => None,
+        VaultError::SyncedStash => None,
+        VaultError::NotEncrypted => None,
+        VaultError::TimeoutTooLong => None,
+        VaultError::ReadKeyFile(_) => Some(1301),
+        VaultError::WriteKeyFile(_) => Some(1302),
+        VaultError::Unlock(e) => crypto_error_code(e),
+        VaultError::InvalidKey(_) => None,
+        VaultError::ReadSessionFile(_) => Some(1303),
+        VaultError::WriteSessionFile(_) => Some(1304),
+        VaultError::RemoveSessionFile(_) => Some(1305),
+        VaultError::ScheduleSessionExpiry(_) => Some(1314),
+        VaultError::ReadStash(_) => Some(1306),
+        VaultError::WriteStash(_) => Some(1307),
+        VaultError::RemoveStash(_) => Some(1308),
+        VaultError::Encrypt(e) => crypto_error_code(e),
+        VaultError::Decrypt(e) => crypto_error_code(e),
+        VaultError::Archive(_) => Some(1309),
+        VaultError::Extract(_) => Some(1310),
+        VaultError::CreateWorkDir(_) => Some(1311),
+        VaultError::RemovePlaintext(_) => Some(1312),
+    }
+}
+
+fn sync_error_code(error: &SyncError) -> Option<u16> {
+    match error {
+        SyncError::RunGit(_) => None,
+        SyncError::Git(_, _) => None,
+        SyncError::AlreadySetUp => None,
+        SyncError::NotSetUp => None,
+        SyncError::Conflicts(_) => None,
+        SyncError::ConfigureRepo(_) => Some(1900),
+        SyncError::SetPermissions(_) => Some(1901),
+    }
+}
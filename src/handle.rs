@@ -1,69 +1,420 @@
 use crate::errors::AppError::{self, *};
 
-use crate::actions::{delete_content_items, empty_stash, get_content, list_content, push_content};
-use crate::args::{Action, Args};
+use crate::actions::{
+    AppendPosition, StashedCommand, SyncDirError, append_content, clear_clipboard, collect_garbage,
+    delete_content_items, empty_stash, export_content, find_duplicates, get_content,
+    import_content, list_content, list_template_vars, push_content, run_content, run_doctor,
+    sync_with_dir,
+};
+use crate::args::{Action, Args, SyncCommand};
+use crate::clipboard::ClipboardProvider;
+use crate::completions::write_completions;
+use crate::config::{Config, load_config};
+use crate::crypto::KeySource;
+use crate::daemon::{DaemonResponse, ask_daemon, run_daemon};
+use crate::help::{show_help, write_man_pages};
+use crate::mcp::run_mcp_server;
+use crate::permissions::create_private_dir;
+use crate::serve::serve;
+use crate::sync::Repo;
+use crate::vault::Vault;
 use dirs::data_dir;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 const DATA_DIR: &str = "tash";
 const DATA_DIR_ENV_VAR: &str = "TASH_DATA_DIR";
 
 pub fn handle(args: Args) -> Result<(), AppError> {
-    let data_dir = match std::env::var(DATA_DIR_ENV_VAR) {
-        Ok(data_dir_from_env_var) => PathBuf::from(data_dir_from_env_var),
+    // these don't need the data directory
+    match args.action {
+        Action::Completions { shell } => {
+            return write_completions(shell, &mut std::io::stdout()).map_err(WriteCompletions);
+        }
+        Action::Help { path } => return show_help(&path).map_err(ShowHelp),
+        Action::Man { dir } => return write_man_pages(dir.as_deref()).map_err(WriteManPages),
+        _ => {}
+    }
+
+    let data_dir = get_data_dir()?;
+    let vault = Vault::new(&data_dir).map_err(AccessVault)?;
+    // asked before anything else is set up, so that commands the daemon serves stay fast
+    let served = ask_daemon(&vault, &args.action);
+
+    if !data_dir.exists() {
+        create_private_dir(&data_dir).map_err(CreateDataDir)?;
+    }
+
+    let config = load_config().map_err(LoadConfig)?;
+    let clipboard_provider = ClipboardProvider::from_env();
+    let key_source = KeySource::new(config.identity_file.clone());
+    let repo = Repo::new(&data_dir);
+
+    match args.action {
+        Action::ClearClipboard { after_ms } => {
+            return clear_clipboard(&clipboard_provider, Duration::from_millis(after_ms))
+                .map_err(ClearClipboard);
+        }
+        Action::Daemon => {
+            return run_daemon(&data_dir, &vault, &key_source).map_err(RunDaemon);
+        }
+        Action::DecryptStash => {
+            vault.decrypt(&key_source).map_err(DecryptStash)?;
+            return commit_for_sync(&repo, &config);
+        }
+        Action::EncryptStash => {
+            vault.encrypt(&key_source).map_err(EncryptStash)?;
+            return commit_for_sync(&repo, &config);
+        }
+        Action::ExpireSession { after_ms } => {
+            return vault
+                .expire_session(Duration::from_millis(after_ms))
+                .map_err(LockStash);
+        }
+        Action::Lock => return vault.lock().map_err(LockStash),
+        Action::Unlock { timeout } => {
+            return vault.unlock(&key_source, timeout).map_err(UnlockStash);
+        }
+        Action::Mcp { allow_sensitive } => {
+            // a prompt would stall every message until it's answered
+            let key_source = key_source.without_prompts();
+            return run_mcp_server(&vault, &repo, &config, &key_source, allow_sensitive)
+                .map_err(RunMcpServer);
+        }
+        Action::Serve { bind } => {
+            // a prompt would block every request until it's answered
+            let key_source = key_source.without_prompts();
+            return serve(&bind, &vault, &repo, &config, &key_source).map_err(Serve);
+        }
+        Action::Sync {
+            command,
+            prefer,
+            dir: None,
+        } => {
+            return match command {
+                Some(SyncCommand::Init { remote }) => repo.init(&remote, prefer).map_err(SetUpSync),
+                None => repo.sync(prefer).map_err(SyncStash),
+            };
+        }
+        // the directory would end up with the stash's decrypted contents
+        Action::Sync { dir: Some(_), .. } if vault.is_encrypted() => {
+            return Err(SyncWithDir(SyncDirError::EncryptedStash));
+        }
+        _ => {}
+    }
+
+    let stash = vault.open(&key_source).map_err(OpenStash)?;
+    let result = handle_stash_action(
+        args.action,
+        served,
+        stash.dir(),
+        &config,
+        &clipboard_provider,
+        &key_source,
+    );
+    stash.close().map_err(SaveStash)?;
+
+    let exit_code = match result? {
+        AfterStash::Exit(exit_code) => exit_code,
+        AfterStash::Run(command) => command.run().map_err(RunContent)?,
+    };
+    commit_for_sync(&repo, &config)?;
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+
+    Ok(())
+}
+
+/// Commits changes made to the stash, if it's synced and changes aren't meant to be batched.
+pub(crate) fn commit_for_sync(repo: &Repo, config: &Config) -> Result<(), AppError> {
+    if repo.is_set_up() && !config.sync_batch_commits {
+        repo.commit_changes().map_err(CommitForSync)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the data directory, which is `TASH_DATA_DIR` if set, and `tash` in the user's data
+/// directory otherwise.
+pub(crate) fn get_data_dir() -> Result<PathBuf, AppError> {
+    match std::env::var(DATA_DIR_ENV_VAR) {
+        Ok(data_dir_from_env_var) => Ok(PathBuf::from(data_dir_from_env_var)),
         Err(e) => match e {
             std::env::VarError::NotPresent => {
                 let user_data_dir = data_dir().ok_or(GetDataDir)?;
-                user_data_dir.join(PathBuf::from(DATA_DIR))
+                Ok(user_data_dir.join(PathBuf::from(DATA_DIR)))
             }
-            std::env::VarError::NotUnicode(_) => return Err(DataDirEnvVarInvalid(e)),
+            std::env::VarError::NotUnicode(_) => Err(DataDirEnvVarInvalid(e)),
         },
-    };
-
-    if !data_dir.exists() {
-        std::fs::create_dir_all(&data_dir).map_err(CreateDataDir)?;
     }
+}
 
-    match args.action {
+/// Runs `f` with the stash's contents, opening (and closing) the stash the way a single command
+/// would. Used by long-running commands that handle many requests.
+pub(crate) fn with_stash<T>(
+    vault: &Vault,
+    key_source: &KeySource,
+    f: impl FnOnce(&Path) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let stash = vault.open(key_source).map_err(OpenStash)?;
+    let result = f(stash.dir());
+    stash.close().map_err(SaveStash)?;
+
+    result
+}
+
+/// What's left to do once the stash is closed.
+enum AfterStash {
+    Exit(i32),
+    /// stashed commands may well use tash themselves, which would have to wait for an encrypted
+    /// stash to be closed
+    Run(StashedCommand),
+}
+
+/// Handles actions that work with the stash's contents, which are in `data_dir` (using what the
+/// daemon served for the action, if anything).
+fn handle_stash_action(
+    action: Action,
+    served: Option<DaemonResponse>,
+    data_dir: &PathBuf,
+    config: &Config,
+    clipboard_provider: &ClipboardProvider,
+    key_source: &KeySource,
+) -> Result<AfterStash, AppError> {
+    match action {
+        // these don't need the stash's contents, and are handled before it's opened
+        Action::ClearClipboard { .. }
+        | Action::Completions { .. }
+        | Action::Daemon
+        | Action::DecryptStash
+        | Action::EncryptStash
+        | Action::ExpireSession { .. }
+        | Action::Help { .. }
+        | Action::Lock
+        | Action::Man { .. }
+        | Action::Mcp { .. }
+        | Action::Serve { .. }
+        | Action::Sync { dir: None, .. }
+        | Action::Unlock { .. } => {}
+        Action::Sync {
+            dir: Some(dir),
+            prefer,
+            ..
+        } => {
+            sync_with_dir(data_dir, &dir, prefer, config.dedupe).map_err(SyncWithDir)?;
+        }
+        Action::Append {
+            key,
+            data,
+            file_path,
+            get_content_from_clipboard,
+            selection,
+            separator,
+            strict,
+            verbose,
+        } => {
+            append_content(
+                data_dir,
+                &key,
+                data.as_deref(),
+                file_path.as_deref(),
+                get_content_from_clipboard,
+                clipboard_provider,
+                selection.or(config.selection).unwrap_or_default(),
+                separator.as_deref(),
+                AppendPosition::End,
+                key_source,
+                strict,
+                &config.secret_patterns,
+                config.compress,
+                config.dedupe,
+                verbose,
+            )
+            .map_err(AppendContent)?;
+        }
         Action::Delete { keys } => {
-            delete_content_items(&data_dir, &keys).map_err(DeleteContent)?;
+            delete_content_items(data_dir, &keys).map_err(DeleteContent)?;
+        }
+        Action::Doctor { fix } => {
+            let healthy = run_doctor(data_dir, fix).map_err(RunDoctor)?;
+            if !healthy {
+                return Ok(AfterStash::Exit(1));
+            }
         }
         Action::Empty { skip_confirmation } => {
-            empty_stash(&data_dir, skip_confirmation).map_err(EmptyStash)?;
+            empty_stash(data_dir, skip_confirmation).map_err(EmptyStash)?;
+        }
+        Action::Export {
+            keys,
+            output,
+            format,
+            prefix,
+            encrypt,
+        } => {
+            export_content(
+                data_dir,
+                &keys,
+                prefix.as_deref(),
+                &output,
+                format,
+                encrypt,
+                key_source,
+            )
+            .map_err(ExportContent)?;
+        }
+        Action::Import {
+            path,
+            on_conflict,
+            dry_run,
+            skip_confirmation,
+        } => {
+            import_content(
+                data_dir,
+                &path,
+                on_conflict,
+                dry_run,
+                skip_confirmation,
+                key_source,
+                config.compress,
+                config.dedupe,
+            )
+            .map_err(ImportContent)?;
+        }
+        Action::FindDupes => {
+            find_duplicates(data_dir).map_err(FindDuplicates)?;
+        }
+        Action::Gc => {
+            collect_garbage(data_dir).map_err(CollectGarbage)?;
         }
         Action::Get {
             key,
             no_output,
             copy_to_clipboard,
+            selection,
+            clear_clipboard_after,
             pop,
+            vars,
+            vars_from_env,
+            pretty,
+            force,
         } => {
-            get_content(&data_dir, &key, no_output, copy_to_clipboard, pop).map_err(GetContent)?;
+            get_content(
+                data_dir,
+                &key,
+                served.and_then(DaemonResponse::into_content),
+                no_output,
+                copy_to_clipboard,
+                clipboard_provider,
+                selection.or(config.selection).unwrap_or_default(),
+                clear_clipboard_after,
+                pop,
+                key_source,
+                &vars,
+                vars_from_env,
+                pretty,
+                force,
+            )
+            .map_err(GetContent)?;
         }
-        Action::LS => {
-            list_content(&data_dir).map_err(ListContent)?;
+        Action::LS { long, hash } => {
+            list_content(
+                data_dir,
+                long,
+                hash,
+                served.and_then(DaemonResponse::into_entries),
+            )
+            .map_err(ListContent)?;
+        }
+        Action::Prepend {
+            key,
+            data,
+            file_path,
+            get_content_from_clipboard,
+            selection,
+            separator,
+            strict,
+            verbose,
+        } => {
+            append_content(
+                data_dir,
+                &key,
+                data.as_deref(),
+                file_path.as_deref(),
+                get_content_from_clipboard,
+                clipboard_provider,
+                selection.or(config.selection).unwrap_or_default(),
+                separator.as_deref(),
+                AppendPosition::Start,
+                key_source,
+                strict,
+                &config.secret_patterns,
+                config.compress,
+                config.dedupe,
+                verbose,
+            )
+            .map_err(AppendContent)?;
         }
         Action::Push {
             key,
             data,
             file_path,
             get_content_from_clipboard,
+            selection,
             prevent_overwrite,
+            sensitive,
+            encrypt,
+            compress,
+            strict,
             echo,
             verbose,
         } => {
             push_content(
-                &data_dir,
+                data_dir,
                 &key,
                 data.as_deref(),
                 file_path.as_deref(),
                 get_content_from_clipboard,
+                clipboard_provider,
+                selection.or(config.selection).unwrap_or_default(),
                 prevent_overwrite,
+                sensitive,
+                encrypt,
+                compress || config.compress,
+                key_source,
+                strict,
+                &config.secret_patterns,
+                config.dedupe,
                 echo,
                 verbose,
             )
             .map_err(PushContent)?;
         }
+        Action::Run {
+            key,
+            args,
+            shell,
+            skip_confirmation,
+            vars,
+            vars_from_env,
+        } => {
+            let command = run_content(
+                data_dir,
+                &key,
+                &args,
+                shell.as_deref(),
+                skip_confirmation,
+                key_source,
+                &vars,
+                vars_from_env,
+            )
+            .map_err(RunContent)?;
+            return Ok(AfterStash::Run(command));
+        }
+        Action::Vars { key } => {
+            list_template_vars(data_dir, &key, key_source).map_err(ListTemplateVars)?;
+        }
     }
 
-    Ok(())
+    Ok(AfterStash::Exit(0))
 }
@@ -0,0 +1,194 @@
+use crate::args::Args;
+use clap::CommandFactory;
+use clap_mangen::Man;
+use clap_mangen::roff::{Roff, roman};
+use std::io::Error as IOError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum HelpError {
+    #[error(
+        "there's no help topic or command called \"{0}\" (topics: keys, config, env, error-codes)"
+    )]
+    UnknownTopic(String),
+    #[error("couldn't write help: {0}")]
+    WriteHelp(IOError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManError {
+    #[error("couldn't create directory {0}: {1}")]
+    CreateDir(PathBuf, IOError),
+    #[error("couldn't write man page {0}: {1}")]
+    WritePage(String, IOError),
+}
+
+/// Help on things that don't belong to a single command. Each one is shown via "tash help
+/// <TOPIC>", and gets a section in the man page.
+#[derive(Clone, Copy)]
+enum Topic {
+    Keys,
+    Config,
+    Env,
+    ErrorCodes,
+}
+
+const TOPICS: [Topic; 4] = [Topic::Keys, Topic::Config, Topic::Env, Topic::ErrorCodes];
+
+impl Topic {
+    fn name(&self) -> &'static str {
+        match self {
+            Topic::Keys => "keys",
+            Topic::Config => "config",
+            Topic::Env => "env",
+            Topic::ErrorCodes => "error-codes",
+        }
+    }
+
+    fn summary(&self) -> &'static str {
+        match self {
+            Topic::Keys => "Rules keys need to follow",
+            Topic::Config => "Settings tash's config file supports",
+            Topic::Env => "Environment variables tash reads",
+            Topic::ErrorCodes => "What the codes reported with unexpected errors mean",
+        }
+    }
+
+    fn man_section(&self) -> &'static str {
+        match self {
+            Topic::Keys => "KEYS",
+            Topic::Config => "CONFIGURATION",
+            Topic::Env => "ENVIRONMENT",
+            Topic::ErrorCodes => "ERROR CODES",
+        }
+    }
+
+    /// Paragraphs are separated by blank lines; indented ones are shown as they are.
+    fn text(&self) -> &'static str {
+        match self {
+            Topic::Keys => include_str!("keys.txt"),
+            Topic::Config => include_str!("config.txt"),
+            Topic::Env => include_str!("env.txt"),
+            Topic::ErrorCodes => include_str!("error-codes.txt"),
+        }
+    }
+}
+
+/// Prints help for the command in `path` (eg. ["sync", "init"]), or for the topic it names. The
+/// top-level help, followed by a list of topics, is printed if `path` is empty.
+pub fn show_help(path: &[String]) -> Result<(), HelpError> {
+    let mut stdout = std::io::stdout();
+
+    if let [name] = path
+        && let Some(topic) = TOPICS.iter().find(|t| t.name() == name)
+    {
+        return stdout
+            .write_all(topic.text().as_bytes())
+            .map_err(HelpError::WriteHelp);
+    }
+
+    let cmd = Args::command();
+    let mut subcommand = &cmd;
+    for name in path {
+        subcommand = subcommand
+            .find_subcommand(name)
+            .filter(|c| !c.is_hide_set())
+            .ok_or_else(|| HelpError::UnknownTopic(path.join(" ")))?;
+    }
+
+    // clap decides between the short and the long help (and their layouts) when handling --help,
+    // so it's left to do so here as well
+    let args = std::iter::once("tash")
+        .chain(path.iter().map(String::as_str))
+        .chain(std::iter::once("--help"));
+    if let Err(help) = cmd.try_get_matches_from(args) {
+        help.print().map_err(HelpError::WriteHelp)?;
+    }
+    if path.is_empty() {
+        let mut topics = String::from("\nHelp topics (tash help <TOPIC>):\n");
+        for topic in TOPICS {
+            topics.push_str(&format!("  {:<13}  {}\n", topic.name(), topic.summary()));
+        }
+        stdout
+            .write_all(topics.as_bytes())
+            .map_err(HelpError::WriteHelp)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the man page for tash (which includes the help topics) to stdout, or, if `dir` is
+/// provided, writes it along with a man page for every command to `dir`.
+pub fn write_man_pages(dir: Option<&Path>) -> Result<(), ManError> {
+    let mut cmd = Args::command();
+    cmd.build();
+
+    let Some(dir) = dir else {
+        return render_man_page(&cmd, &mut std::io::stdout())
+            .map_err(|e| ManError::WritePage("to stdout".to_string(), e));
+    };
+
+    std::fs::create_dir_all(dir).map_err(|e| ManError::CreateDir(dir.to_path_buf(), e))?;
+    write_man_page(&cmd, dir, render_man_page)?;
+    for subcommand in visible_subcommands(&cmd) {
+        write_command_man_pages(subcommand, dir)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the man page for a command, and ones for its subcommands (eg. tash-sync-init.1).
+fn write_command_man_pages(cmd: &clap::Command, dir: &Path) -> Result<(), ManError> {
+    write_man_page(cmd, dir, |cmd, w| man(cmd).render(w))?;
+    for subcommand in visible_subcommands(cmd) {
+        write_command_man_pages(subcommand, dir)?;
+    }
+
+    Ok(())
+}
+
+fn write_man_page(
+    cmd: &clap::Command,
+    dir: &Path,
+    render: impl Fn(&clap::Command, &mut dyn Write) -> Result<(), IOError>,
+) -> Result<(), ManError> {
+    let path = dir.join(man(cmd).get_filename());
+    let mut page = Vec::new();
+    render(cmd, &mut page)
+        .and_then(|_| std::fs::write(&path, page))
+        .map_err(|e| ManError::WritePage(path.to_string_lossy().to_string(), e))
+}
+
+fn man(cmd: &clap::Command) -> Man {
+    Man::new(cmd.clone()).source(format!("tash {}", env!("CARGO_PKG_VERSION")))
+}
+
+fn visible_subcommands(cmd: &clap::Command) -> impl Iterator<Item = &clap::Command> {
+    cmd.get_subcommands().filter(|c| !c.is_hide_set())
+}
+
+/// Renders the man page for tash itself, which is what clap_mangen generates, with a section for
+/// every help topic added.
+fn render_man_page(cmd: &clap::Command, w: &mut dyn Write) -> Result<(), IOError> {
+    man(cmd).render(w)?;
+
+    let mut roff = Roff::default();
+    for topic in TOPICS {
+        roff.control("SH", [topic.man_section()]);
+        for paragraph in topic.text().split("\n\n") {
+            let lines = paragraph.lines().collect::<Vec<_>>();
+            if lines.iter().all(|l| l.starts_with("  ")) {
+                roff.control("PP", []).control("nf", []);
+                for line in lines {
+                    roff.text([roman(line.strip_prefix("  ").unwrap_or(line))]);
+                }
+                roff.control("fi", []);
+            } else {
+                roff.control("PP", []).text([roman(lines.join(" "))]);
+            }
+        }
+    }
+
+    roff.to_writer(w)
+}
@@ -1,13 +1,30 @@
 mod actions;
 mod args;
+mod clipboard;
+mod completions;
+mod config;
+mod crypto;
+mod daemon;
 mod errors;
 mod handle;
+mod help;
+mod mcp;
+mod permissions;
+mod serve;
+mod sync;
+mod vault;
 
 use args::Args;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use completions::COMPLETE_ENV_VAR;
 use handle::handle;
 
 fn main() {
+    CompleteEnv::with_factory(Args::command)
+        .var(COMPLETE_ENV_VAR)
+        .complete();
+
     let args = Args::parse();
     let result = handle(args);
 
@@ -0,0 +1,363 @@
+use crate::actions::{
+    ContentType, GetError, entry_details, is_key_valid, list_entries, read_content, search_entries,
+    store_content, validate_key,
+};
+use crate::config::Config;
+use crate::crypto::KeySource;
+use crate::errors::AppError::{self, *};
+use crate::handle::{commit_for_sync, with_stash};
+use crate::sync::Repo;
+use crate::vault::Vault;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::Error as IOError;
+use std::io::{BufRead, Write};
+
+/// Versions of the Model Context Protocol the server speaks; the last one is preferred.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2024-11-05", "2025-03-26", "2025-06-18"];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+#[derive(thiserror::Error, Debug)]
+pub enum McpError {
+    #[error("couldn't read message: {0}")]
+    ReadMessage(IOError),
+    #[error("couldn't write message: {0}")]
+    WriteMessage(IOError),
+}
+
+#[derive(Deserialize)]
+struct Message {
+    id: Option<Value>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    query: String,
+}
+
+#[derive(Deserialize)]
+struct GetArgs {
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct PushArgs {
+    key: String,
+    content: String,
+    #[serde(default)]
+    sensitive: bool,
+    #[serde(default)]
+    encrypt: bool,
+    #[serde(default)]
+    compress: bool,
+    #[serde(default)]
+    prevent_overwrite: bool,
+    #[serde(default)]
+    strict: bool,
+}
+
+/// A Model Context Protocol server, with tools for working with the stash.
+///
+/// Messages are JSON-RPC objects, one per line, read from stdin and written to stdout. Content of
+/// sensitive and encrypted entries is only handed out (or overwritten) if `allow_sensitive` is
+/// set (by whoever started the server, rather than by the client).
+struct Server<'a> {
+    vault: &'a Vault,
+    repo: &'a Repo,
+    config: &'a Config,
+    key_source: &'a KeySource,
+    allow_sensitive: bool,
+}
+
+/// Serves the Model Context Protocol over stdio, until stdin is closed.
+pub fn run_mcp_server(
+    vault: &Vault,
+    repo: &Repo,
+    config: &Config,
+    key_source: &KeySource,
+    allow_sensitive: bool,
+) -> Result<(), McpError> {
+    let server = Server {
+        vault,
+        repo,
+        config,
+        key_source,
+        allow_sensitive,
+    };
+
+    let mut stdout = std::io::stdout();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(McpError::ReadMessage)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(response) = server.respond(&line) else {
+            continue;
+        };
+        let mut response = serde_json::to_vec(&response)
+            .map_err(IOError::other)
+            .map_err(McpError::WriteMessage)?;
+        response.push(b'\n');
+        stdout
+            .write_all(&response)
+            .and_then(|_| stdout.flush())
+            .map_err(McpError::WriteMessage)?;
+    }
+
+    Ok(())
+}
+
+impl Server<'_> {
+    /// Returns the response to a message, if it needs one (notifications don't).
+    fn respond(&self, line: &str) -> Option<Value> {
+        let message: Message = match serde_json::from_str(line) {
+            Ok(m) => m,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, e.to_string())),
+        };
+        let (Some(id), Some(method)) = (message.id, message.method) else {
+            return None;
+        };
+
+        let result = match method.as_str() {
+            "initialize" => Ok(self.initialize(&message.params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(message.params),
+            _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = requested
+            .filter(|v| PROTOCOL_VERSIONS.contains(v))
+            .unwrap_or(PROTOCOL_VERSIONS[PROTOCOL_VERSIONS.len() - 1]);
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "tash", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Calls that fail because of the stash (eg. a missing key) are reported as tool results, so
+    /// that clients can act on them; malformed calls are reported as JSON-RPC errors.
+    fn call_tool(&self, params: Value) -> Result<Value, (i32, String)> {
+        let call: ToolCall = parse_params(params)?;
+        let result = match call.name.as_str() {
+            "list_entries" => self.list(),
+            "search_entries" => self.search(parse_params(call.arguments)?),
+            "get_entry" => self.get(parse_params(call.arguments)?),
+            "push_entry" => self.push(parse_params(call.arguments)?),
+            name => return Err((INVALID_PARAMS, format!("unknown tool: {name}"))),
+        };
+
+        Ok(match result {
+            Ok(content) => json!({ "content": [content], "isError": false }),
+            Err(message) => json!({
+                "content": [{ "type": "text", "text": message }],
+                "isError": true,
+            }),
+        })
+    }
+
+    fn list(&self) -> Result<Value, String> {
+        let entries = with_stash(self.vault, self.key_source, |dir| {
+            list_entries(dir, false).map_err(ListContent)
+        })
+        .map_err(|e| e.to_string())?;
+
+        json_text(&entries)
+    }
+
+    fn search(&self, args: SearchArgs) -> Result<Value, String> {
+        let entries = with_stash(self.vault, self.key_source, |dir| {
+            search_entries(dir, &args.query, self.key_source, self.allow_sensitive)
+                .map_err(SearchEntries)
+        })
+        .map_err(|e| e.to_string())?;
+
+        json_text(&entries)
+    }
+
+    fn get(&self, args: GetArgs) -> Result<Value, String> {
+        let content = with_stash(self.vault, self.key_source, |dir| {
+            if !is_key_valid(&args.key) || !dir.join(&args.key).is_file() {
+                return Err(GetContent(GetError::KeyDoesntExist));
+            }
+            let details = entry_details(dir, args.key.clone(), false).map_err(ListContent)?;
+            if (details.sensitive || details.encrypted) && !self.allow_sensitive {
+                return Ok(None);
+            }
+
+            read_content(dir, &args.key, self.key_source)
+                .map(Some)
+                .map_err(GetContent)
+        })
+        .map_err(|e| e.to_string())?;
+
+        let Some(content) = content else {
+            return Err(format!(
+                "\"{}\" is sensitive or encrypted; its content is only handed out if the server is started with --allow-sensitive",
+                args.key
+            ));
+        };
+
+        match content.content_type {
+            ContentType::Png => Ok(json!({
+                "type": "image",
+                "data": BASE64.encode(&content.contents),
+                "mimeType": ContentType::Png.as_str(),
+            })),
+            ContentType::Binary => Err(format!(
+                "content of type {} can't be handed out",
+                content.content_type
+            )),
+            ContentType::PlainText | ContentType::Json | ContentType::Yaml => {
+                match String::from_utf8(content.contents) {
+                    Ok(text) => Ok(json!({ "type": "text", "text": text })),
+                    Err(e) => Err(format!("content is not valid text: {e}")),
+                }
+            }
+        }
+    }
+
+    fn push(&self, args: PushArgs) -> Result<Value, String> {
+        let stash = |dir: &std::path::Path| -> Result<bool, AppError> {
+            validate_key(dir, &args.key, args.prevent_overwrite).map_err(PushContent)?;
+            if dir.join(&args.key).is_file() {
+                let details = entry_details(dir, args.key.clone(), false).map_err(ListContent)?;
+                if (details.sensitive || details.encrypted) && !self.allow_sensitive {
+                    return Ok(false);
+                }
+            }
+
+            store_content(
+                dir,
+                &args.key,
+                args.content.as_bytes(),
+                None,
+                args.sensitive,
+                args.encrypt,
+                args.compress || self.config.compress,
+                self.key_source,
+                args.strict,
+                &self.config.secret_patterns,
+                self.config.dedupe,
+            )
+            .map_err(PushContent)?;
+            Ok(true)
+        };
+        let stashed = with_stash(self.vault, self.key_source, stash).map_err(|e| e.to_string())?;
+        if !stashed {
+            return Err(format!(
+                "\"{}\" is sensitive or encrypted; it can only be overwritten if the server is started with --allow-sensitive",
+                args.key
+            ));
+        }
+        commit_for_sync(self.repo, self.config).map_err(|e| e.to_string())?;
+
+        Ok(json!({
+            "type": "text",
+            "text": format!("stashed {} bytes under \"{}\"", args.content.len(), args.key),
+        }))
+    }
+}
+
+fn tools() -> Value {
+    let key = json!({
+        "type": "string",
+        "description": "key of the entry (needs to conform to the regex ^[a-z0-9_-]{1,30}$)",
+    });
+
+    json!([
+        {
+            "name": "list_entries",
+            "description": "List entries in the stash, along with their details (size, content type, flags)",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "search_entries",
+            "description": "Find entries whose keys or text content contain a query (ignoring case)",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "text to look for" },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "get_entry",
+            "description": "Get the content of an entry",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "key": key },
+                "required": ["key"],
+            },
+        },
+        {
+            "name": "push_entry",
+            "description": "Stash text content under a key (replacing the entry if it exists)",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "key": key,
+                    "content": { "type": "string", "description": "content to stash" },
+                    "sensitive": { "type": "boolean", "description": "mark the entry as sensitive" },
+                    "encrypt": { "type": "boolean", "description": "encrypt the content" },
+                    "compress": { "type": "boolean", "description": "compress the content" },
+                    "prevent_overwrite": {
+                        "type": "boolean",
+                        "description": "fail if the key already exists",
+                    },
+                    "strict": {
+                        "type": "boolean",
+                        "description": "refuse to stash unencrypted content that looks like it contains secrets",
+                    },
+                },
+                "required": ["key", "content"],
+            },
+        },
+    ])
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, (i32, String)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, format!("invalid params: {e}")))
+}
+
+fn json_text<T: serde::Serialize>(value: &T) -> Result<Value, String> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+
+    Ok(json!({ "type": "text", "text": text }))
+}
+
+fn error_response(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
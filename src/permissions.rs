@@ -0,0 +1,44 @@
+use std::fs::{DirBuilder, File, OpenOptions};
+use std::io::Error as IOError;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::Path;
+
+/// Mode for directories that hold stashed content: accessible only by the current user.
+pub const PRIVATE_DIR_MODE: u32 = 0o700;
+/// Mode for files that hold stashed content (or details about it): readable and writable only by
+/// the current user.
+pub const PRIVATE_FILE_MODE: u32 = 0o600;
+/// Suffix of files that are written to before being moved into place.
+pub const TEMP_FILE_SUFFIX: &str = ".tmp";
+
+/// Creates a directory (and its parents) that only the current user can access. Modes of
+/// directories that already exist are left alone.
+pub fn create_private_dir(path: &Path) -> Result<(), IOError> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(PRIVATE_DIR_MODE)
+        .create(path)
+}
+
+/// Creates (or truncates) a file for writing. Files that get created can only be accessed by the
+/// current user; modes of existing files are left alone.
+pub fn create_private_file(path: &Path) -> Result<File, IOError> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(PRIVATE_FILE_MODE)
+        .open(path)
+}
+
+/// Writes to a temporary file first, so that a failed write doesn't leave a partial file behind.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), IOError> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(TEMP_FILE_SUFFIX);
+
+    let mut file = create_private_file(Path::new(&temp_path))?;
+    file.write_all(contents)?;
+
+    std::fs::rename(&temp_path, path)
+}
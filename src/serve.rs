@@ -0,0 +1,320 @@
+use crate::actions::{
+    CONTENT_MAX_BYTES, DeleteError, GetError, PushError, delete_content_items, is_key_valid,
+    list_entries, read_content, search_entries, store_content, validate_key,
+};
+use crate::config::Config;
+use crate::crypto::KeySource;
+use crate::errors::AppError::{self, *};
+use crate::handle::{commit_for_sync, with_stash};
+use crate::sync::Repo;
+use crate::vault::Vault;
+use serde::Serialize;
+use std::io::Read;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServeError {
+    #[error("no token is configured for the API; set \"serve_token\" in tash's config")]
+    NoToken,
+    #[error("couldn't listen on {0}: {1}")]
+    Bind(String, Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Body of every error response. `code` is the one tash would exit with, for unexpected errors.
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+    code: Option<u16>,
+}
+
+struct ApiResponse {
+    status: u16,
+    body: Vec<u8>,
+    content_type: &'static str,
+}
+
+impl ApiResponse {
+    fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self {
+                status: 200,
+                body,
+                content_type: "application/json",
+            },
+            Err(e) => Self::error(500, format!("couldn't serialize response: {e}"), None),
+        }
+    }
+
+    fn content(body: Vec<u8>, content_type: &'static str) -> Self {
+        Self {
+            status: 200,
+            body,
+            content_type,
+        }
+    }
+
+    fn empty(status: u16) -> Self {
+        Self {
+            status,
+            body: Vec::new(),
+            content_type: "text/plain",
+        }
+    }
+
+    fn error(status: u16, error: String, code: Option<u16>) -> Self {
+        Self {
+            status,
+            body: serde_json::to_vec(&ErrorBody { error, code }).unwrap_or_default(),
+            content_type: "application/json",
+        }
+    }
+}
+
+impl From<AppError> for ApiResponse {
+    fn from(error: AppError) -> Self {
+        let status = match &error {
+            GetContent(GetError::KeyDoesntExist) | DeleteContent(DeleteError::KeysDontExist(_)) => {
+                404
+            }
+            PushContent(PushError::KeyAlreadyExists) => 409,
+            PushContent(PushError::ContentTooLarge(_)) => 413,
+            _ if error.code().is_some() => 500,
+            _ => 400,
+        };
+
+        Self::error(status, error.to_string(), error.code())
+    }
+}
+
+/// A local HTTP API over the stash, for editor plugins and scripts. Every request works with the
+/// stash the way a single command would: it's opened (and closed) for each one, and changes are
+/// committed for sync afterwards.
+struct Api<'a> {
+    vault: &'a Vault,
+    repo: &'a Repo,
+    config: &'a Config,
+    key_source: &'a KeySource,
+    token: &'a str,
+}
+
+/// Serves the API on `bind` until the process is stopped. Requests need to carry the token from
+/// the config as a bearer token.
+pub fn serve(
+    bind: &str,
+    vault: &Vault,
+    repo: &Repo,
+    config: &Config,
+    key_source: &KeySource,
+) -> Result<(), ServeError> {
+    let token = config
+        .serve_token
+        .as_deref()
+        .filter(|t| !t.is_empty())
+        .ok_or(ServeError::NoToken)?;
+    let server = Server::http(bind).map_err(|e| ServeError::Bind(bind.to_string(), e))?;
+    match server.server_addr().to_ip() {
+        Some(addr) => println!("listening on http://{addr}"),
+        None => println!("listening on {bind}"),
+    }
+
+    let api = Api {
+        vault,
+        repo,
+        config,
+        key_source,
+        token,
+    };
+    for mut request in server.incoming_requests() {
+        let response = api.respond(&mut request);
+
+        // query strings are left out, since they can hold search terms
+        let path = request.url().split('?').next().unwrap_or_default();
+        println!("{} {path} {}", request.method(), response.status);
+
+        let mut http_response =
+            Response::from_data(response.body).with_status_code(response.status);
+        if let Ok(header) = Header::from_bytes("Content-Type", response.content_type) {
+            http_response = http_response.with_header(header);
+        }
+        if let Err(e) = request.respond(http_response) {
+            eprintln!("couldn't send response: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+impl Api<'_> {
+    fn respond(&self, request: &mut Request) -> ApiResponse {
+        if !self.is_authorized(request) {
+            return ApiResponse::error(401, "missing or invalid bearer token".to_string(), None);
+        }
+
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+        let result = match (request.method(), segments.as_slice()) {
+            (Method::Get, ["entries"]) => self.list(),
+            (Method::Get, ["entries", key]) => self.get(key),
+            (Method::Put, ["entries", key]) => self.push(key, query, request),
+            (Method::Delete, ["entries", key]) => self.delete(key),
+            (Method::Get, ["search"]) => self.search(query),
+            (_, ["entries"] | ["entries", _] | ["search"]) => Err(ApiResponse::error(
+                405,
+                "method not allowed".to_string(),
+                None,
+            )),
+            _ => Err(ApiResponse::error(404, "not found".to_string(), None)),
+        };
+
+        result.unwrap_or_else(|response| response)
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+            .is_some_and(|token| tokens_match(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    fn list(&self) -> Result<ApiResponse, ApiResponse> {
+        let entries = with_stash(self.vault, self.key_source, |dir| {
+            list_entries(dir, true).map_err(ListContent)
+        })?;
+
+        Ok(ApiResponse::json(&entries))
+    }
+
+    fn get(&self, key: &str) -> Result<ApiResponse, ApiResponse> {
+        let content = with_stash(self.vault, self.key_source, |dir| {
+            if !is_key_valid(key) {
+                return Err(GetContent(GetError::KeyDoesntExist));
+            }
+            read_content(dir, key, self.key_source).map_err(GetContent)
+        })?;
+
+        Ok(ApiResponse::content(
+            content.contents,
+            content.content_type.as_str(),
+        ))
+    }
+
+    /// The request's body is stashed as is; flags "push" accepts can be passed as query
+    /// parameters (eg. ?sensitive&encrypt).
+    fn push(
+        &self,
+        key: &str,
+        query: &str,
+        request: &mut Request,
+    ) -> Result<ApiResponse, ApiResponse> {
+        // reading stops right past the limit, so that oversized bodies aren't held in memory
+        let body_length = request.body_length();
+        let mut content = Vec::new();
+        request
+            .as_reader()
+            .take(CONTENT_MAX_BYTES as u64 + 1)
+            .read_to_end(&mut content)
+            .map_err(|e| {
+                ApiResponse::error(400, format!("couldn't read request body: {e}"), None)
+            })?;
+        if content.len() > CONTENT_MAX_BYTES {
+            let size = body_length.unwrap_or(content.len());
+            return Err(PushContent(PushError::ContentTooLarge(size)).into());
+        }
+
+        let created = with_stash(self.vault, self.key_source, |dir| {
+            validate_key(dir, key, query_flag(query, "prevent-overwrite")).map_err(PushContent)?;
+            let created = !dir.join(key).exists();
+            store_content(
+                dir,
+                key,
+                &content,
+                None,
+                query_flag(query, "sensitive"),
+                query_flag(query, "encrypt"),
+                query_flag(query, "compress") || self.config.compress,
+                self.key_source,
+                query_flag(query, "strict"),
+                &self.config.secret_patterns,
+                self.config.dedupe,
+            )
+            .map_err(PushContent)?;
+            Ok(created)
+        })?;
+        commit_for_sync(self.repo, self.config)?;
+
+        Ok(ApiResponse::empty(if created { 201 } else { 204 }))
+    }
+
+    fn delete(&self, key: &str) -> Result<ApiResponse, ApiResponse> {
+        with_stash(self.vault, self.key_source, |dir| {
+            if !is_key_valid(key) {
+                return Err(DeleteContent(DeleteError::KeysDontExist(vec![
+                    key.to_string(),
+                ])));
+            }
+            delete_content_items(dir, &vec![key.to_string()]).map_err(DeleteContent)
+        })?;
+        commit_for_sync(self.repo, self.config)?;
+
+        Ok(ApiResponse::empty(204))
+    }
+
+    fn search(&self, query: &str) -> Result<ApiResponse, ApiResponse> {
+        let term = query_param(query, "q")
+            .filter(|q| !q.is_empty())
+            .ok_or_else(|| {
+                ApiResponse::error(400, "missing query parameter \"q\"".to_string(), None)
+            })?;
+        let entries = with_stash(self.vault, self.key_source, |dir| {
+            search_entries(dir, &term, self.key_source, true).map_err(SearchEntries)
+        })?;
+
+        Ok(ApiResponse::json(&entries))
+    }
+}
+
+/// Compares tokens without bailing out at the first differing byte, so that response times don't
+/// hint at how much of a token is right.
+fn tokens_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Flags are set when they're present without a value, or with any value apart from "false" and
+/// "0".
+fn query_flag(query: &str, name: &str) -> bool {
+    query_param(query, name).is_some_and(|v| v != "false" && v != "0")
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .find(|(n, _)| *n == name)
+        .map(|(_, value)| decode_query_value(value))
+}
+
+fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (b, escaped) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(escaped)) => {
+                decoded.push(escaped);
+                i += 2;
+            }
+            _ => decoded.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,318 @@
+use crate::actions::{entry_key, entry_paths};
+use crate::permissions::{PRIVATE_DIR_MODE, PRIVATE_FILE_MODE, TEMP_FILE_SUFFIX};
+use std::collections::BTreeSet;
+use std::fs::Permissions;
+use std::io::Error as IOError;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory git keeps its repository in; the rest of tash leaves it alone.
+pub const GIT_DIR: &str = ".git";
+const REMOTE: &str = "origin";
+const BRANCH: &str = "main";
+const REMOTE_BRANCH_REF: &str = "refs/remotes/origin/main";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SyncError {
+    #[error("couldn't run git (is it installed?): {0}")]
+    RunGit(IOError),
+    #[error("\"git {0}\" failed: {1}")]
+    Git(String, String),
+    #[error("sync is already set up for this stash")]
+    AlreadySetUp,
+    #[error("sync isn't set up for this stash; set it up via \"tash sync init <REMOTE>\"")]
+    NotSetUp,
+    #[error(
+        "entries changed both locally and remotely: {0:?} (use --prefer local or --prefer remote to resolve them)"
+    )]
+    Conflicts(Vec<String>),
+    #[error("couldn't configure the stash's repository: {0}")]
+    ConfigureRepo(IOError),
+    #[error("couldn't restrict permissions of synced files: {0}")]
+    SetPermissions(IOError),
+}
+
+/// Which side to keep for entries that changed both locally and remotely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SyncPreference {
+    /// keep the local version
+    Local,
+    /// keep the remote version
+    Remote,
+}
+
+/// Git-backed sync of the data directory.
+///
+/// The data directory doubles as a git repository whose "main" branch is synced with a single
+/// remote. Changes are committed after every command that makes them (or only when syncing, if
+/// "sync_batch_commits" is set), and syncing merges the remote's changes before pushing local
+/// ones. Entries that changed on both sides are reported per key, and are only resolved when a
+/// side to prefer is provided. git never merges files itself, since an entry's content and its
+/// metadata (which holds the content's checksum) only make sense together.
+pub struct Repo {
+    dir: PathBuf,
+}
+
+impl Repo {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.to_path_buf(),
+        }
+    }
+
+    pub fn is_set_up(&self) -> bool {
+        self.dir.join(GIT_DIR).is_dir()
+    }
+
+    /// Turns the data directory into a repository that syncs with `remote`, and syncs it.
+    pub fn init(&self, remote: &str, prefer: Option<SyncPreference>) -> Result<(), SyncError> {
+        if self.is_set_up() {
+            return Err(SyncError::AlreadySetUp);
+        }
+
+        // the repository holds every version of every entry, so it's kept as private as they are
+        self.git(&["init", "-q", "--shared=0600", "--initial-branch", BRANCH])?;
+        std::fs::set_permissions(
+            self.dir.join(GIT_DIR),
+            Permissions::from_mode(PRIVATE_DIR_MODE),
+        )
+        .map_err(SyncError::ConfigureRepo)?;
+        std::fs::write(
+            self.dir.join(GIT_DIR).join("info").join("exclude"),
+            format!("*{TEMP_FILE_SUFFIX}\n"),
+        )
+        .map_err(SyncError::ConfigureRepo)?;
+        self.git(&["remote", "add", REMOTE, remote])?;
+
+        // commits need an identity; if git doesn't have one, the repository gets its own
+        if !self
+            .run_git(&["var", "GIT_COMMITTER_IDENT"])?
+            .status
+            .success()
+        {
+            self.git(&["config", "user.name", "tash"])?;
+            self.git(&["config", "user.email", "tash@localhost"])?;
+        }
+
+        self.sync(prefer)
+    }
+
+    /// Commits local changes, merges the remote's changes, and pushes the result.
+    pub fn sync(&self, prefer: Option<SyncPreference>) -> Result<(), SyncError> {
+        if !self.is_set_up() {
+            return Err(SyncError::NotSetUp);
+        }
+
+        // repositories set up by older versions of tash don't have this yet
+        std::fs::write(
+            self.dir.join(GIT_DIR).join("info").join("attributes"),
+            "* -merge\n",
+        )
+        .map_err(SyncError::ConfigureRepo)?;
+
+        self.commit_changes()?;
+        self.git(&["fetch", "-q", REMOTE])?;
+
+        let head_before = self.rev("HEAD")?;
+        if self.rev(REMOTE_BRANCH_REF)?.is_some() {
+            self.merge(prefer)?;
+        }
+        let head_after = self.rev("HEAD")?;
+
+        let Some(head_after) = head_after else {
+            println!("nothing to sync");
+            return Ok(());
+        };
+
+        // local changes are committed before merging, so HEAD only moves if changes were pulled
+        let pulled_paths = match &head_before {
+            Some(head_before) if *head_before == head_after => Vec::new(),
+            Some(head_before) => {
+                self.git_paths(&["diff", "--name-only", "-z", head_before, &head_after])?
+            }
+            None => self.git_paths(&["ls-tree", "-r", "--name-only", "-z", &head_after])?,
+        };
+        let unpushed_count = match self.rev(REMOTE_BRANCH_REF)? {
+            Some(_) => self.git(&["rev-list", "--count", &format!("{REMOTE_BRANCH_REF}..HEAD")])?,
+            None => self.git(&["rev-list", "--count", "HEAD"])?,
+        };
+        let unpushed_count = unpushed_count.trim().parse::<usize>().unwrap_or_default();
+
+        if unpushed_count > 0 {
+            self.git(&["push", "-q", "-u", REMOTE, BRANCH])?;
+        }
+        restrict_permissions(&self.dir).map_err(SyncError::SetPermissions)?;
+
+        if !pulled_paths.is_empty() {
+            let keys = keys_for_paths(&pulled_paths);
+            if keys.is_empty() {
+                println!("pulled changes");
+            } else {
+                println!(
+                    "pulled changes to: {}",
+                    keys.into_iter().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+        if unpushed_count > 0 {
+            let commit_str = if unpushed_count == 1 {
+                "commit"
+            } else {
+                "commits"
+            };
+            println!("pushed {unpushed_count} {commit_str}");
+        }
+        if pulled_paths.is_empty() && unpushed_count == 0 {
+            println!("already in sync");
+        }
+
+        Ok(())
+    }
+
+    /// Commits every change made to the stash since the last commit. Returns whether there were
+    /// any.
+    pub fn commit_changes(&self) -> Result<bool, SyncError> {
+        self.git(&["add", "-A"])?;
+        let staged_paths = self.git_paths(&["diff", "--cached", "--name-only", "-z"])?;
+        if staged_paths.is_empty() {
+            return Ok(false);
+        }
+
+        let keys = keys_for_paths(&staged_paths);
+        let message = if keys.is_empty() {
+            "update stash".to_string()
+        } else {
+            format!("update {}", keys.into_iter().collect::<Vec<_>>().join(", "))
+        };
+        self.git(&["commit", "-q", "-m", &message])?;
+
+        Ok(true)
+    }
+
+    fn merge(&self, prefer: Option<SyncPreference>) -> Result<(), SyncError> {
+        let args = [
+            "merge",
+            "-q",
+            "--no-edit",
+            "--allow-unrelated-histories",
+            REMOTE_BRANCH_REF,
+        ];
+        let output = self.run_git(&args)?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let unmerged = self.git_paths(&["diff", "--name-only", "--diff-filter=U", "-z"])?;
+        if unmerged.is_empty() {
+            return Err(SyncError::Git(
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        let Some(prefer) = prefer else {
+            self.git(&["merge", "--abort"])?;
+            let conflicts = unmerged
+                .iter()
+                .map(|path| entry_key(path).unwrap_or_else(|| path.clone()))
+                .collect::<BTreeSet<_>>();
+            return Err(SyncError::Conflicts(conflicts.into_iter().collect()));
+        };
+
+        // conflicting entries are taken from one side as a whole, even if only one of their files
+        // conflicted, so that their content and metadata still match
+        let mut paths = BTreeSet::new();
+        for path in unmerged {
+            match entry_key(&path) {
+                Some(key) => paths.extend(entry_paths(&key)),
+                None => {
+                    paths.insert(path);
+                }
+            }
+        }
+
+        let side = match prefer {
+            SyncPreference::Local => "HEAD",
+            SyncPreference::Remote => "MERGE_HEAD",
+        };
+        for path in &paths {
+            let exists_on_side = self
+                .run_git(&["cat-file", "-e", &format!("{side}:{path}")])?
+                .status
+                .success();
+            if exists_on_side {
+                self.git(&["checkout", side, "--", path])?;
+            } else {
+                self.git(&["rm", "-q", "-f", "--ignore-unmatch", "--", path])?;
+            }
+        }
+        self.git(&["commit", "-q", "--no-edit"])?;
+
+        Ok(())
+    }
+
+    fn rev(&self, name: &str) -> Result<Option<String>, SyncError> {
+        let output = self.run_git(&["rev-parse", "-q", "--verify", name])?;
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn git_paths(&self, args: &[&str]) -> Result<Vec<String>, SyncError> {
+        Ok(self
+            .git(args)?
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, SyncError> {
+        let output = self.run_git(args)?;
+        if !output.status.success() {
+            return Err(SyncError::Git(
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn run_git(&self, args: &[&str]) -> Result<std::process::Output, SyncError> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .map_err(SyncError::RunGit)
+    }
+}
+
+/// Returns the keys of the entries that paths in the repository belong to. Blobs don't belong to a
+/// single entry, and files of an encrypted stash don't reveal any.
+fn keys_for_paths(paths: &[String]) -> BTreeSet<String> {
+    paths.iter().filter_map(|path| entry_key(path)).collect()
+}
+
+/// git creates files according to the umask; files it brought in are made private again.
+fn restrict_permissions(dir: &Path) -> Result<(), IOError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.ends_with(GIT_DIR) {
+            continue;
+        }
+
+        let metadata = std::fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            std::fs::set_permissions(&path, Permissions::from_mode(PRIVATE_DIR_MODE))?;
+            restrict_permissions(&path)?;
+        } else if metadata.is_file() && metadata.permissions().mode() & 0o077 != 0 {
+            std::fs::set_permissions(&path, Permissions::from_mode(PRIVATE_FILE_MODE))?;
+        }
+    }
+
+    Ok(())
+}
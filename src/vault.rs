@@ -0,0 +1,515 @@
+use crate::crypto::{
+    CryptoError, EncryptionScheme, KeySource, decrypt_with_identity, encrypt_for_identity,
+};
+use crate::permissions::{create_private_dir, write_private_file};
+use crate::sync::GIT_DIR;
+use age::secrecy::ExposeSecret;
+use age::x25519;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Error as IOError;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const STASH_FILE: &str = "stash.age";
+pub const KEY_FILE: &str = "stash.key";
+const RUNTIME_DIR: &str = "tash";
+const RUNTIME_DIR_ENV_VAR: &str = "TASH_RUNTIME_DIR";
+/// Name of the (hidden) subcommand that runs [`Vault::expire_session`].
+const EXPIRE_SESSION_COMMAND: &str = "expire-session";
+
+#[derive(thiserror::Error, Debug)]
+pub enum VaultError {
+    #[error("TASH_RUNTIME_DIR value is invalid: {0}")]
+    RuntimeDirEnvVarInvalid(std::env::VarError),
+    #[error("couldn't create runtime directory: {0}")]
+    CreateRuntimeDir(IOError),
+    #[error("runtime directory {0} needs to be owned by, and only accessible to, the current user")]
+    RuntimeDirNotPrivate(PathBuf),
+    #[error("couldn't lock the stash: {0}")]
+    Lock(IOError),
+    #[error("stash is already encrypted")]
+    AlreadyEncrypted,
+    #[error(
+        "stash is synced via git, and its history (and remote) would still hold its entries and keys in plaintext; encrypt the stash before setting up sync instead"
+    )]
+    SyncedStash,
+    #[error("stash is not encrypted")]
+    NotEncrypted,
+    #[error("timeout is too long")]
+    TimeoutTooLong,
+    #[error("couldn't read the stash's key file: {0}")]
+    ReadKeyFile(IOError),
+    #[error("couldn't write the stash's key file: {0}")]
+    WriteKeyFile(IOError),
+    #[error("couldn't decrypt the stash's key: {0}")]
+    Unlock(CryptoError),
+    #[error("the stash's key is invalid: {0}")]
+    InvalidKey(String),
+    #[error("couldn't read session file: {0}")]
+    ReadSessionFile(IOError),
+    #[error("couldn't write session file: {0}")]
+    WriteSessionFile(IOError),
+    #[error("couldn't remove session file: {0}")]
+    RemoveSessionFile(IOError),
+    #[error("couldn't schedule the session's expiry: {0}")]
+    ScheduleSessionExpiry(IOError),
+    #[error("couldn't read encrypted stash: {0}")]
+    ReadStash(IOError),
+    #[error("couldn't write encrypted stash: {0}")]
+    WriteStash(IOError),
+    #[error("couldn't remove encrypted stash: {0}")]
+    RemoveStash(IOError),
+    #[error(transparent)]
+    Encrypt(CryptoError),
+    #[error(transparent)]
+    Decrypt(CryptoError),
+    #[error("couldn't archive the stash's contents: {0}")]
+    Archive(IOError),
+    #[error("couldn't extract the stash's contents: {0}")]
+    Extract(IOError),
+    #[error("couldn't create working directory: {0}")]
+    CreateWorkDir(IOError),
+    #[error("couldn't remove unencrypted content: {0}")]
+    RemovePlaintext(IOError),
+}
+
+/// Whole-stash encryption.
+///
+/// An encrypted stash is made up of two files in the data directory: `stash.age`, an archive of
+/// every entry (along with its metadata) encrypted with a key generated by tash, and `stash.key`,
+/// that key encrypted with a passphrase. Neither reveals the stash's keys.
+///
+/// Commands extract the archive into a private working directory in the runtime directory, and
+/// write it back once they're done (if its contents changed); a lock file keeps commands from
+/// doing so at the same time. "tash unlock" stores the decrypted key in a session file next to it,
+/// so that the passphrase doesn't have to be entered for every command until the session expires
+/// (at which point a helper process removes it).
+pub struct Vault {
+    data_dir: PathBuf,
+    runtime_dir: PathBuf,
+    session_file: PathBuf,
+    daemon_socket: PathBuf,
+    lock_file: PathBuf,
+    work_dir_prefix: String,
+}
+
+impl Vault {
+    pub fn new(data_dir: &Path) -> Result<Self, VaultError> {
+        let runtime_dir = match std::env::var(RUNTIME_DIR_ENV_VAR) {
+            Ok(dir) => PathBuf::from(dir),
+            // the session file holds the stash's key, so it only goes in directories that are
+            // private to the user (and, preferably, don't outlive their login session)
+            Err(std::env::VarError::NotPresent) => match dirs::runtime_dir() {
+                Some(dir) => dir.join(RUNTIME_DIR),
+                None => std::env::temp_dir().join(format!("{RUNTIME_DIR}-{}", current_uid())),
+            },
+            Err(e) => return Err(VaultError::RuntimeDirEnvVarInvalid(e)),
+        };
+
+        // sessions (and daemons) are per data directory; the name only needs to be stable while
+        // one lasts
+        let mut hasher = DefaultHasher::new();
+        std::fs::canonicalize(data_dir)
+            .unwrap_or_else(|_| data_dir.to_path_buf())
+            .hash(&mut hasher);
+        let id = format!("{:016x}", hasher.finish());
+        let session_file = runtime_dir.join(format!("{id}.key"));
+        let daemon_socket = runtime_dir.join(format!("{id}.sock"));
+        let lock_file = runtime_dir.join(format!("{id}.lock"));
+        let work_dir_prefix = format!("{id}.work-");
+
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            runtime_dir,
+            session_file,
+            daemon_socket,
+            lock_file,
+            work_dir_prefix,
+        })
+    }
+
+    /// Path of the socket "tash daemon" listens on for this stash.
+    pub fn daemon_socket(&self) -> &Path {
+        &self.daemon_socket
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.data_dir.join(KEY_FILE).exists()
+    }
+
+    /// Moves the stash's contents into an encrypted archive, protected by a new passphrase.
+    pub fn encrypt(&self, key_source: &KeySource) -> Result<(), VaultError> {
+        if self.is_encrypted() {
+            return Err(VaultError::AlreadyEncrypted);
+        }
+        // encrypting the stash is meant to hide its keys, which git's history can't be made to
+        if self.data_dir.join(GIT_DIR).is_dir() {
+            return Err(VaultError::SyncedStash);
+        }
+
+        let identity = x25519::Identity::generate();
+        let encrypted_key = key_source
+            .encrypt(
+                EncryptionScheme::Passphrase,
+                identity.to_string().expose_secret().as_bytes(),
+            )
+            .map_err(VaultError::Encrypt)?;
+
+        let archive = archive_dir(&self.data_dir)?;
+        let encrypted_stash =
+            encrypt_for_identity(&identity, &archive).map_err(VaultError::Encrypt)?;
+
+        write_private_file(&self.data_dir.join(STASH_FILE), &encrypted_stash)
+            .map_err(VaultError::WriteStash)?;
+        // the key file marks the stash as encrypted, so it goes in last
+        write_private_file(&self.data_dir.join(KEY_FILE), &encrypted_key)
+            .map_err(VaultError::WriteKeyFile)?;
+
+        for entry in std::fs::read_dir(&self.data_dir).map_err(VaultError::RemovePlaintext)? {
+            let path = entry.map_err(VaultError::RemovePlaintext)?.path();
+            if path.ends_with(STASH_FILE) || path.ends_with(KEY_FILE) {
+                continue;
+            }
+
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path).map_err(VaultError::RemovePlaintext)?;
+            } else {
+                std::fs::remove_file(&path).map_err(VaultError::RemovePlaintext)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turns an encrypted stash back into a regular one.
+    pub fn decrypt(&self, key_source: &KeySource) -> Result<(), VaultError> {
+        if !self.is_encrypted() {
+            return Err(VaultError::NotEncrypted);
+        }
+
+        let _lock = self.acquire_lock()?;
+        let identity = self.identity(key_source)?;
+        let archive = self.read_archive(&identity)?;
+        extract_archive(&archive, &self.data_dir)?;
+
+        std::fs::remove_file(self.data_dir.join(KEY_FILE)).map_err(VaultError::RemoveStash)?;
+        std::fs::remove_file(self.data_dir.join(STASH_FILE)).map_err(VaultError::RemoveStash)?;
+
+        self.lock()
+    }
+
+    /// Keeps the stash's key around for `timeout`, so that commands don't need the passphrase.
+    pub fn unlock(&self, key_source: &KeySource, timeout: Duration) -> Result<(), VaultError> {
+        if !self.is_encrypted() {
+            return Err(VaultError::NotEncrypted);
+        }
+
+        let expires_at = SystemTime::now()
+            .checked_add(timeout)
+            .ok_or(VaultError::TimeoutTooLong)?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let identity = self.identity_from_passphrase(key_source)?;
+
+        self.create_runtime_dir()?;
+        write_private_file(
+            &self.session_file,
+            format!("{expires_at}\n{}\n", identity.to_string().expose_secret()).as_bytes(),
+        )
+        .map_err(VaultError::WriteSessionFile)?;
+
+        schedule_session_expiry(timeout).map_err(VaultError::ScheduleSessionExpiry)
+    }
+
+    /// Entry point for the helper process spawned by "tash unlock"; removes the session once it
+    /// has expired. Sessions that were renewed in the meantime are left alone.
+    pub fn expire_session(&self, after: Duration) -> Result<(), VaultError> {
+        std::thread::sleep(after);
+        self.identity_from_session().map(|_| ())
+    }
+
+    /// Forgets the stash's key, if it was unlocked.
+    pub fn lock(&self) -> Result<(), VaultError> {
+        match std::fs::remove_file(&self.session_file) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(VaultError::RemoveSessionFile(e)),
+        }
+    }
+
+    /// Makes the stash's contents available in a directory. Regular stashes are used in place.
+    pub fn open(&self, key_source: &KeySource) -> Result<OpenStash, VaultError> {
+        if !self.is_encrypted() {
+            return Ok(OpenStash {
+                dir: self.data_dir.clone(),
+                encrypted: None,
+            });
+        }
+
+        let lock = self.acquire_lock()?;
+        let identity = self.identity(key_source)?;
+        let archive = self.read_archive(&identity)?;
+
+        // working directories left behind by commands that were killed are cleaned up here; no
+        // other command can be using one while the lock is held
+        self.remove_work_dirs()?;
+        let work_dir =
+            self.runtime_dir
+                .join(format!("{}{}", self.work_dir_prefix, std::process::id()));
+        create_private_dir(&work_dir).map_err(VaultError::CreateWorkDir)?;
+
+        if let Err(e) = extract_archive(&archive, &work_dir) {
+            let _ = std::fs::remove_dir_all(&work_dir);
+            return Err(e);
+        }
+
+        Ok(OpenStash {
+            dir: work_dir,
+            encrypted: Some(EncryptedStash {
+                identity,
+                archive,
+                stash_file: self.data_dir.join(STASH_FILE),
+                _lock: lock,
+            }),
+        })
+    }
+
+    /// Waits for other commands to be done with the encrypted stash, and keeps it to this one
+    /// until the returned file is dropped.
+    fn acquire_lock(&self) -> Result<File, VaultError> {
+        self.create_runtime_dir()?;
+        let lock = File::create(&self.lock_file).map_err(VaultError::Lock)?;
+        lock.lock().map_err(VaultError::Lock)?;
+
+        Ok(lock)
+    }
+
+    fn remove_work_dirs(&self) -> Result<(), VaultError> {
+        for entry in std::fs::read_dir(&self.runtime_dir).map_err(VaultError::CreateWorkDir)? {
+            let entry = entry.map_err(VaultError::CreateWorkDir)?;
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&self.work_dir_prefix)
+            {
+                std::fs::remove_dir_all(entry.path()).map_err(VaultError::CreateWorkDir)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn identity(&self, key_source: &KeySource) -> Result<x25519::Identity, VaultError> {
+        match self.identity_from_session()? {
+            Some(identity) => Ok(identity),
+            None => self.identity_from_passphrase(key_source),
+        }
+    }
+
+    fn identity_from_session(&self) -> Result<Option<x25519::Identity>, VaultError> {
+        let contents = match std::fs::read_to_string(&self.session_file) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(VaultError::ReadSessionFile(e)),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let session = contents.split_once('\n').and_then(|(expires_at, key)| {
+            let expires_at = expires_at.parse::<u64>().ok()?;
+            (expires_at > now).then_some(key.trim())
+        });
+
+        match session {
+            Some(key) => parse_identity(key).map(Some),
+            None => {
+                // expired (or unreadable) sessions are cleaned up
+                self.lock()?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn identity_from_passphrase(
+        &self,
+        key_source: &KeySource,
+    ) -> Result<x25519::Identity, VaultError> {
+        let encrypted_key =
+            std::fs::read(self.data_dir.join(KEY_FILE)).map_err(VaultError::ReadKeyFile)?;
+        let key = key_source
+            .decrypt(EncryptionScheme::Passphrase, &encrypted_key)
+            .map_err(VaultError::Unlock)?;
+        let key = String::from_utf8(key).map_err(|e| VaultError::InvalidKey(e.to_string()))?;
+
+        parse_identity(key.trim())
+    }
+
+    fn read_archive(&self, identity: &x25519::Identity) -> Result<Vec<u8>, VaultError> {
+        let encrypted_stash =
+            std::fs::read(self.data_dir.join(STASH_FILE)).map_err(VaultError::ReadStash)?;
+
+        decrypt_with_identity(identity, &encrypted_stash).map_err(VaultError::Decrypt)
+    }
+
+    /// Creates the runtime directory, and makes sure that other users can't get at what's in it
+    /// (it might've been created by someone else, eg. in a shared temporary directory).
+    pub fn create_runtime_dir(&self) -> Result<(), VaultError> {
+        create_private_dir(&self.runtime_dir).map_err(VaultError::CreateRuntimeDir)?;
+
+        let metadata =
+            std::fs::symlink_metadata(&self.runtime_dir).map_err(VaultError::CreateRuntimeDir)?;
+        if !metadata.is_dir() || metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+            return Err(VaultError::RuntimeDirNotPrivate(self.runtime_dir.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// The stash's contents, as made available by [`Vault::open`].
+pub struct OpenStash {
+    dir: PathBuf,
+    encrypted: Option<EncryptedStash>,
+}
+
+struct EncryptedStash {
+    identity: x25519::Identity,
+    archive: Vec<u8>,
+    stash_file: PathBuf,
+    // released once the working directory is gone
+    _lock: File,
+}
+
+impl OpenStash {
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Writes changes made to an encrypted stash's contents back to it.
+    pub fn close(self) -> Result<(), VaultError> {
+        let Some(encrypted) = &self.encrypted else {
+            return Ok(());
+        };
+
+        // "tash empty" removes the directory altogether
+        let archive = if self.dir.exists() {
+            archive_dir(&self.dir)?
+        } else {
+            tar::Builder::new(Vec::new())
+                .into_inner()
+                .map_err(VaultError::Archive)?
+        };
+
+        if archive == encrypted.archive {
+            return Ok(());
+        }
+
+        let encrypted_stash =
+            encrypt_for_identity(&encrypted.identity, &archive).map_err(VaultError::Encrypt)?;
+        write_private_file(&encrypted.stash_file, &encrypted_stash).map_err(VaultError::WriteStash)
+    }
+}
+
+impl Drop for OpenStash {
+    fn drop(&mut self) {
+        if self.encrypted.is_some() {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions, and can't fail
+    unsafe { libc::geteuid() }
+}
+
+/// Spawns a detached tash process that removes the session file once `timeout` is up.
+fn schedule_session_expiry(timeout: Duration) -> Result<(), IOError> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .arg(EXPIRE_SESSION_COMMAND)
+        .arg("--after-ms")
+        .arg(timeout.as_millis().to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // keep the helper alive when the terminal that started it goes away
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    command.spawn()?;
+
+    Ok(())
+}
+
+fn parse_identity(key: &str) -> Result<x25519::Identity, VaultError> {
+    key.parse::<x25519::Identity>()
+        .map_err(|e| VaultError::InvalidKey(e.to_string()))
+}
+
+/// Archives every file under `dir`. The archive only depends on the files' paths and contents (or
+/// targets, for symlinks), so that unchanged contents can be detected.
+fn archive_dir(dir: &Path) -> Result<Vec<u8>, VaultError> {
+    let mut paths = Vec::new();
+    collect_files(dir, dir, &mut paths).map_err(VaultError::Archive)?;
+    paths.sort();
+
+    let mut builder = tar::Builder::new(Vec::new());
+    for path in paths {
+        // entries that link to blobs stay links, so that content isn't stored more than once
+        if let Ok(target) = std::fs::read_link(dir.join(&path)) {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_mode(0o777);
+            builder
+                .append_link(&mut header, &path, &target)
+                .map_err(VaultError::Archive)?;
+            continue;
+        }
+
+        let contents = std::fs::read(dir.join(&path)).map_err(VaultError::Archive)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o600);
+        builder
+            .append_data(&mut header, &path, contents.as_slice())
+            .map_err(VaultError::Archive)?;
+    }
+
+    builder.into_inner().map_err(VaultError::Archive)
+}
+
+fn collect_files(root: &Path, dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), IOError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if dir == root && path.ends_with(GIT_DIR) {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(root, &path, paths)?;
+        } else if let Ok(relative_path) = path.strip_prefix(root) {
+            paths.push(relative_path.to_path_buf());
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_archive(archive: &[u8], dir: &Path) -> Result<(), VaultError> {
+    let mut archive = tar::Archive::new(archive);
+    archive.set_preserve_mtime(false);
+    archive.unpack(dir).map_err(VaultError::Extract)
+}
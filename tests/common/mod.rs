@@ -1,28 +1,42 @@
 use insta_cmd::get_cargo_bin;
 use std::ffi::OsStr;
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdout, Command, Stdio};
 use tempfile::{TempDir, tempdir};
 
 #[cfg(test)]
 pub struct Fixture {
-    _tmp_dir: TempDir,
-    tmp_dir_str: String,
+    tmp_dir: TempDir,
+    data_dir: PathBuf,
+    clipboard_file: PathBuf,
+    primary_selection_file: PathBuf,
+    config_file: PathBuf,
+    runtime_dir: PathBuf,
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 impl Fixture {
     #[allow(clippy::expect_used)]
     pub fn new() -> Self {
         let tmp_dir = tempdir().expect("temporary directory should've been created");
-        let tmp_dir_str = tmp_dir
-            .path()
-            .to_str()
-            .expect("temporary directory should've been converted to a string")
-            .to_string();
+        let data_dir = tmp_dir.path().join("data");
+        let clipboard_file = tmp_dir.path().join("clipboard");
+        let primary_selection_file = tmp_dir.path().join("clipboard.primary");
+        let config_file = tmp_dir.path().join("tash.toml");
+        let runtime_dir = tmp_dir.path().join("runtime");
 
         Self {
-            _tmp_dir: tmp_dir,
-            tmp_dir_str,
+            tmp_dir,
+            data_dir,
+            clipboard_file,
+            primary_selection_file,
+            config_file,
+            runtime_dir,
         }
     }
 
@@ -32,8 +46,291 @@ impl Fixture {
         S: AsRef<OsStr>,
     {
         let mut cmd = Command::new(get_cargo_bin("tash"));
-        cmd.env("TASH_DATA_DIR", &self.tmp_dir_str);
+        cmd.env("TASH_DATA_DIR", &self.data_dir);
+        cmd.env("TASH_FAKE_CLIPBOARD_FILE", &self.clipboard_file);
+        cmd.env("TASH_CONFIG_PATH", &self.config_file);
+        cmd.env("TASH_RUNTIME_DIR", &self.runtime_dir);
+        cmd.env_remove("TASH_SHELL");
+        cmd.env_remove("TASH_PASSPHRASE");
+        cmd.env_remove("TASH_IDENTITY_FILE");
+        // "tash sync" runs git, which shouldn't pick up the user's configuration
+        cmd.env("GIT_CONFIG_GLOBAL", "/dev/null");
+        cmd.env("GIT_CONFIG_NOSYSTEM", "1");
+        for var in ["GIT_AUTHOR", "GIT_COMMITTER"] {
+            cmd.env(format!("{var}_NAME"), "tash");
+            cmd.env(format!("{var}_EMAIL"), "tash@example.com");
+        }
         cmd.args(args);
         cmd
     }
+
+    #[allow(clippy::expect_used)]
+    pub fn set_clipboard_content<C: AsRef<[u8]>>(&self, content: C) {
+        std::fs::write(&self.clipboard_file, content)
+            .expect("clipboard file should've been written to");
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn clipboard_content(&self) -> Vec<u8> {
+        std::fs::read(&self.clipboard_file).expect("clipboard file should've been read")
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn set_primary_selection_content<C: AsRef<[u8]>>(&self, content: C) {
+        std::fs::write(&self.primary_selection_file, content)
+            .expect("primary selection file should've been written to");
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn primary_selection_content(&self) -> Vec<u8> {
+        std::fs::read(&self.primary_selection_file)
+            .expect("primary selection file should've been read")
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn stashed_content(&self, key: &str) -> Vec<u8> {
+        std::fs::read(self.data_dir.join(key)).expect("stashed file should've been read")
+    }
+
+    pub fn data_dir_entries(&self) -> Vec<String> {
+        self.data_dir_entries_in("")
+    }
+
+    /// Lists entries of a directory inside the data directory ("" is the data directory itself).
+    #[allow(clippy::expect_used)]
+    pub fn data_dir_entries_in(&self, path: &str) -> Vec<String> {
+        let mut entries = std::fs::read_dir(self.data_dir.join(path))
+            .expect("directory should've been read")
+            .map(|entry| {
+                entry
+                    .expect("data directory entry should've been read")
+                    .file_name()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+
+    /// Lists entries of the runtime directory (which is empty if it doesn't exist yet).
+    #[allow(clippy::expect_used)]
+    pub fn runtime_dir_entries(&self) -> Vec<String> {
+        if !self.runtime_dir.exists() {
+            return Vec::new();
+        }
+
+        let mut entries = std::fs::read_dir(&self.runtime_dir)
+            .expect("runtime directory should've been read")
+            .map(|entry| {
+                entry
+                    .expect("runtime directory entry should've been read")
+                    .file_name()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+
+    /// Writes a file inside the data directory directly (with the mode tash would use).
+    #[allow(clippy::expect_used)]
+    pub fn write_data_dir_file<C: AsRef<[u8]>>(&self, path: &str, contents: C) {
+        let path = self.data_dir.join(path);
+        std::fs::write(&path, contents).expect("file should've been written to");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .expect("permissions should've been set");
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn remove_data_dir_file(&self, path: &str) {
+        std::fs::remove_file(self.data_dir.join(path)).expect("file should've been removed");
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn create_data_dir_dir(&self, path: &str) {
+        let path = self.data_dir.join(path);
+        std::fs::create_dir_all(&path).expect("directory should've been created");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))
+            .expect("permissions should've been set");
+    }
+
+    /// Sets the mode of a path inside the data directory ("" is the data directory itself).
+    #[allow(clippy::expect_used)]
+    pub fn set_data_dir_mode(&self, path: &str, mode: u32) {
+        std::fs::set_permissions(
+            self.data_dir.join(path),
+            std::fs::Permissions::from_mode(mode),
+        )
+        .expect("permissions should've been set");
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn data_dir_mode(&self, path: &str) -> u32 {
+        std::fs::metadata(self.data_dir.join(path))
+            .expect("metadata should've been read")
+            .permissions()
+            .mode()
+            & 0o777
+    }
+
+    /// Returns a path inside the fixture's temporary directory (outside the data directory), for
+    /// files that commands read from or write to.
+    pub fn tmp_path(&self, name: &str) -> PathBuf {
+        self.tmp_dir.path().join(name)
+    }
+
+    /// Creates a bare git repository in the fixture's temporary directory, for "tash sync" to sync
+    /// with.
+    #[allow(clippy::expect_used)]
+    pub fn create_remote(&self, name: &str) -> PathBuf {
+        let path = self.tmp_path(name);
+        let output = Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&path)
+            .output()
+            .expect("git should've been executed");
+        assert!(
+            output.status.success(),
+            "bare repository should've been created"
+        );
+        path
+    }
+
+    /// Returns the subjects of the commits in the data directory's repository, latest first.
+    #[allow(clippy::expect_used)]
+    pub fn commit_subjects(&self) -> Vec<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.data_dir)
+            .args(["log", "--format=%s"])
+            .output()
+            .expect("git should've been executed");
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn write_config(&self, contents: &str) {
+        std::fs::write(&self.config_file, contents).expect("config file should've been written to");
+    }
+
+    /// Starts "tash serve" on a free port; it's stopped when the returned server is dropped.
+    pub fn serve(&self) -> Server {
+        self.start_server(["serve", "--bind", "127.0.0.1:0"])
+    }
+
+    /// Starts "tash daemon"; it's stopped when the returned server is dropped.
+    pub fn daemon(&self) -> Server {
+        self.start_server(["daemon"])
+    }
+
+    #[allow(clippy::expect_used)]
+    fn start_server<const N: usize>(&self, args: [&str; N]) -> Server {
+        let mut child = self
+            .cmd(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("server command should've been executed");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout should've been piped"));
+
+        let mut line = String::new();
+        stdout
+            .read_line(&mut line)
+            .expect("server should've reported its address");
+        let addr = line
+            .trim()
+            .strip_prefix("listening on ")
+            .expect("server should've been listening")
+            .trim_start_matches("http://")
+            .to_string();
+
+        Server {
+            child,
+            _stdout: stdout,
+            addr,
+        }
+    }
+}
+
+/// A running "tash serve" or "tash daemon" process.
+#[cfg(test)]
+pub struct Server {
+    child: Child,
+    // the server logs requests to stdout, so it's kept open
+    _stdout: BufReader<ChildStdout>,
+    addr: String,
+}
+
+#[cfg(test)]
+#[allow(dead_code)]
+impl Server {
+    /// Sends a line to a daemon's socket; returns the line it responds with.
+    #[allow(clippy::expect_used)]
+    pub fn send_line(&self, line: &str) -> String {
+        let mut stream =
+            UnixStream::connect(&self.addr).expect("daemon should've accepted connection");
+        stream
+            .write_all(format!("{line}\n").as_bytes())
+            .expect("request should've been sent");
+
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_line(&mut response)
+            .expect("response should've been read");
+        response.trim_end().to_string()
+    }
+
+    /// Sends a request to the server; returns the status code and body of the response.
+    #[allow(clippy::expect_used)]
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        token: Option<&str>,
+        body: &[u8],
+    ) -> (u16, String) {
+        let mut stream =
+            TcpStream::connect(&self.addr).expect("server should've accepted connection");
+        let mut head = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            self.addr,
+            body.len()
+        );
+        if let Some(token) = token {
+            head.push_str(&format!("Authorization: Bearer {token}\r\n"));
+        }
+        head.push_str("\r\n");
+        stream
+            .write_all(head.as_bytes())
+            .expect("request should've been sent");
+        stream.write_all(body).expect("request should've been sent");
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .expect("response should've been read");
+        let response = String::from_utf8_lossy(&response);
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .expect("response should've had a body");
+        let status = head
+            .split(' ')
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .expect("response should've had a status code");
+
+        (status, body.to_string())
+    }
+}
+
+#[cfg(test)]
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
 }
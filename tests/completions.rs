@@ -0,0 +1,138 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn completions_script_calls_back_into_tash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["completions", "fish"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    complete --keep-order --exclusive --command tash --arguments "(COMPLETE=fish tash -- (commandline --current-process --tokenize --cut-at-cursor) (commandline --current-token))"
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn completions_are_generated_for_bash_and_zsh() {
+    // GIVEN
+    let fx = Fixture::new();
+
+    for shell in ["bash", "zsh"] {
+        let mut cmd = fx.cmd(["completions", shell]);
+
+        // WHEN
+        let output = cmd
+            .output()
+            .expect("completions command should've been executed");
+
+        // THEN
+        assert!(output.status.success());
+        let script = String::from_utf8_lossy(&output.stdout);
+        assert!(script.contains("COMPLETE="), "{shell}: {script}");
+    }
+}
+
+#[test]
+fn keys_are_completed_for_commands_that_take_them() {
+    // GIVEN
+    let fx = Fixture::new();
+    for key in ["key-a", "key-b", "notes"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let mut get_cmd = fx.cmd(["--", "tash", "get", "key"]);
+    get_cmd.env("COMPLETE", "fish");
+    let mut delete_cmd = fx.cmd(["--", "tash", "delete", "key-a", ""]);
+    delete_cmd.env("COMPLETE", "fish");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(get_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a
+    key-b
+
+    ----- stderr -----
+    ");
+    assert_cmd_snapshot!(delete_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a
+    key-b
+    notes
+    --help	Print help
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn keys_of_an_encrypted_stash_arent_completed() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key-a", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+    let mut cmd = fx.cmd(["--", "tash", "get", "key"]);
+    cmd.env("COMPLETE", "fish");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn completions_fail_for_an_unsupported_shell() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["completions", "tcsh"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid value 'tcsh' for '<SHELL>'
+      [possible values: bash, zsh, fish]
+
+      tip: a similar value exists: 'zsh'
+
+    For more information, try '--help'.
+    ");
+}
@@ -0,0 +1,191 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn daemon_serves_entries_over_its_socket() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key-a", "--data", "content for key-a"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let daemon = fx.daemon();
+
+    // WHEN
+    let list_response = daemon.send_line(r#"{"request":"list"}"#);
+    let get_response = daemon.send_line(r#"{"request":"get","key":"key-a"}"#);
+    let missing_response = daemon.send_line(r#"{"request":"get","key":"absent"}"#);
+
+    // THEN
+    insta::assert_snapshot!(list_response, @r#"{"response":"entries","entries":[{"key":"key-a","size":17,"stored_size":17,"sha256":"b6855fab390262082c616af66651639ff69580a7f285ab6f1cb45815124490a5","content_type":"text/plain","sensitive":false,"encrypted":false,"compressed":false}]}"#);
+    insta::assert_snapshot!(get_response, @r#"{"response":"content","content_base64":"Y29udGVudCBmb3Iga2V5LWE=","content_type":"text/plain","sensitive":false}"#);
+    assert_eq!(missing_response, r#"{"response":"fallback"}"#);
+}
+
+#[test]
+fn commands_work_the_same_with_the_daemon_running() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key-a", "--data", "content for key-a", "--compress"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let _daemon = fx.daemon();
+
+    let mut ls_cmd = fx.cmd(["ls", "--long", "--hash"]);
+    let mut get_cmd = fx.cmd(["get", "key-a"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a  17 (26 stored)  b6855fab390262082c616af66651639ff69580a7f285ab6f1cb45815124490a5  text/plain  compressed
+
+    ----- stderr -----
+    ");
+    assert_cmd_snapshot!(get_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    content for key-a
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn daemon_picks_up_changes_made_to_the_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key-a", "--data", "content for key-a"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let daemon = fx.daemon();
+    daemon.send_line(r#"{"request":"list"}"#);
+
+    let mut push_cmd = fx.cmd(["push", "key-b", "--data", "content for key-b"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut delete_cmd = fx.cmd(["delete", "key-a"]);
+    delete_cmd
+        .output()
+        .expect("delete command should've been executed");
+
+    let mut ls_cmd = fx.cmd(["ls"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-b
+
+    ----- stderr -----
+    ");
+    assert!(
+        daemon
+            .send_line(r#"{"request":"get","key":"key-b"}"#)
+            .starts_with(r#"{"response":"content""#)
+    );
+}
+
+#[test]
+fn popping_an_entry_works_with_the_daemon_running() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key-a", "--data", "content for key-a"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let _daemon = fx.daemon();
+
+    let mut cmd = fx.cmd(["get", "key-a", "--pop"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    content for key-a
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries(), vec![".meta"]);
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn errors_are_reported_the_same_with_the_daemon_running() {
+    // GIVEN
+    let fx = Fixture::new();
+    let _daemon = fx.daemon();
+    let mut cmd = fx.cmd(["get", "absent"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: key doesn't exist in stash
+    ");
+}
+
+#[test]
+fn only_one_daemon_can_run_per_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let _daemon = fx.daemon();
+    let mut cmd = fx.cmd(["daemon"]);
+
+    // WHEN
+    // THEN
+    insta::with_settings!({filters => vec![(r"listening on .*\.sock", "listening on [SOCKET]")]}, {
+        assert_cmd_snapshot!(cmd, @r"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+
+        ----- stderr -----
+        Error: couldn't run daemon: a daemon is already running for this stash (listening on [SOCKET])
+        ");
+    });
+}
+
+#[test]
+fn daemon_refuses_to_serve_an_encrypted_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut cmd = fx.cmd(["daemon"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't run daemon: encrypted stashes can't be served by the daemon
+    ");
+}
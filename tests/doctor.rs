@@ -0,0 +1,282 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn doctor_finds_no_issues_in_a_healthy_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["doctor"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no issues found
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn doctor_finds_no_issues_in_a_deduplicated_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    fx.write_config("dedupe = true");
+    for key in ["key-a", "key-b"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["doctor"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no issues found
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn doctor_reports_links_to_missing_blobs() {
+    // GIVEN
+    let fx = Fixture::new();
+    fx.write_config("dedupe = true");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    for blob in fx.data_dir_entries_in(".blobs") {
+        fx.remove_data_dir_file(&format!(".blobs/{blob}"));
+    }
+
+    let mut cmd = fx.cmd(["doctor"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    "key" links to content that doesn't exist
+
+    found 1 issue
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn stashed_content_is_only_accessible_by_the_current_user() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["push", "key", "--data", "content"]);
+
+    // WHEN
+    cmd.output().expect("push command should've been executed");
+
+    // THEN
+    assert_eq!(fx.data_dir_mode(""), 0o700);
+    assert_eq!(fx.data_dir_mode("key"), 0o600);
+    assert_eq!(fx.data_dir_mode(".meta"), 0o700);
+    assert_eq!(fx.data_dir_mode(".meta/key.json"), 0o600);
+}
+
+#[test]
+fn doctor_reports_files_that_arent_entries() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.create_data_dir_dir("notes");
+    fx.write_data_dir_file("notes.txt", "content");
+    fx.write_data_dir_file(".meta/notes.txt", "content");
+
+    let mut cmd = fx.cmd(["doctor", "--fix"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    "notes" is not a regular file, and isn't used by tash
+    "notes.txt" is not named after a valid key (valid regex: ^[a-z0-9_-]{1,30}$), and isn't used by tash
+    ".meta/notes.txt" isn't used by tash
+
+    found 3 issues (0 fixed)
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn doctor_reports_conflicting_versions_kept_by_sync() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("key.conflict-1700000000", "other content");
+
+    let mut cmd = fx.cmd(["doctor"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    "key.conflict-1700000000" is a conflicting version of "key" kept by "tash sync --dir"; remove it once it's been dealt with
+
+    found 1 issue
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn doctor_reports_content_that_doesnt_match_its_checksum() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("key", "corrupted");
+
+    let mut cmd = fx.cmd(["doctor", "--fix"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    "key" doesn't match its recorded checksum, and may be corrupted
+
+    found 1 issue (0 fixed)
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn doctor_fixes_metadata_mismatches_and_leftover_temporary_files() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("legacy", "[1, 2, 3]");
+    fx.write_data_dir_file(".meta/deleted.json", r#"{"content_type":"text/plain"}"#);
+    fx.write_data_dir_file("stash.age.tmp", "partial content");
+
+    let mut cmd = fx.cmd(["doctor", "--fix"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    "legacy" has no metadata [fixed]
+    "stash.age.tmp" is a leftover temporary file [fixed]
+    ".meta/deleted.json" belongs to an entry that doesn't exist [fixed]
+
+    found 3 issues (3 fixed)
+
+    ----- stderr -----
+    "#);
+
+    let mut ls_cmd = fx.cmd(["ls", "--long"]);
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key     7  text/plain
+    legacy  9  application/json
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn doctor_reports_loose_permissions() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.set_data_dir_mode("", 0o755);
+    fx.set_data_dir_mode("key", 0o644);
+
+    let mut cmd = fx.cmd(["doctor"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    data directory is accessible by other users (mode: 755, expected: 700)
+    "key" is accessible by other users (mode: 644, expected: 600)
+
+    found 2 issues; 2 can be fixed via "tash doctor --fix"
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn doctor_fixes_loose_permissions() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.set_data_dir_mode("", 0o755);
+    fx.set_data_dir_mode(".meta/key.json", 0o640);
+
+    let mut cmd = fx.cmd(["doctor", "--fix"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    data directory is accessible by other users (mode: 755, expected: 700) [fixed]
+    ".meta/key.json" is accessible by other users (mode: 640, expected: 600) [fixed]
+
+    found 2 issues (2 fixed)
+
+    ----- stderr -----
+    "#);
+    assert_eq!(fx.data_dir_mode(""), 0o700);
+    assert_eq!(fx.data_dir_mode(".meta/key.json"), 0o600);
+}
@@ -0,0 +1,219 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn exporting_content_to_a_json_bundle_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let pushes = vec![
+        vec!["push", "api-get", "--data", "curl localhost/api"],
+        vec!["push", "api-post", "--data", "[1, 2, 3]", "--sensitive"],
+        vec!["push", "notes", "--data", "some notes"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let export_path = fx.tmp_path("export.json");
+
+    let mut cmd = fx.cmd(["export", "--prefix", "api-", "-o"]);
+    cmd.arg(&export_path);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    exported 2 entries
+
+    ----- stderr -----
+    ");
+    let export = std::fs::read_to_string(&export_path).expect("export should've been read");
+    // entries record when they were last updated
+    insta::with_settings!({filters => vec![(r#""updated_at": \d+"#, r#""updated_at": [TIMESTAMP]"#)]}, {
+        insta::assert_snapshot!(export, @r#"
+        {
+          "version": 1,
+          "entries": [
+            {
+              "key": "api-get",
+              "content": "curl localhost/api",
+              "metadata": {
+                "content_type": "text/plain",
+                "sensitive": false,
+                "encryption": null,
+                "sha256": "4e563a6846cc2ec297e8816084e2c6047be1f250ded27d81120f196a64b667e4",
+                "compression": null,
+                "size": 18,
+                "updated_at": [TIMESTAMP]
+              }
+            },
+            {
+              "key": "api-post",
+              "content": "[1, 2, 3]",
+              "metadata": {
+                "content_type": "application/json",
+                "sensitive": true,
+                "encryption": null,
+                "sha256": "a36b1f2c3f84522dd1005145646617d7054c0851e97c72a039c0bdfac9fa07f3",
+                "compression": null,
+                "size": 9,
+                "updated_at": [TIMESTAMP]
+              }
+            }
+          ]
+        }
+        "#);
+    });
+}
+
+#[test]
+fn exporting_content_to_a_tar_archive_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    for key in ["key-a", "key-b", "key-c"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let export_path = fx.tmp_path("export.tar");
+
+    let mut cmd = fx.cmd(["export", "key-a", "key-c", "-o"]);
+    cmd.arg(&export_path);
+
+    // WHEN
+    let output = cmd
+        .output()
+        .expect("export command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let export = std::fs::read(&export_path).expect("export should've been read");
+    let mut archive = tar::Archive::new(export.as_slice());
+    let paths = archive
+        .entries()
+        .expect("archive entries should've been read")
+        .map(|entry| {
+            entry
+                .expect("archive entry should've been read")
+                .path()
+                .expect("path should've been read")
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec!["key-a", ".meta/key-a.json", "key-c", ".meta/key-c.json"]
+    );
+}
+
+#[test]
+fn exporting_content_keeps_binary_content_intact() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content", "--compress"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let export_path = fx.tmp_path("export.json");
+
+    let mut cmd = fx.cmd(["export", "-o"]);
+    cmd.arg(&export_path);
+
+    // WHEN
+    let output = cmd
+        .output()
+        .expect("export command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let export = std::fs::read_to_string(&export_path).expect("export should've been read");
+    assert!(export.contains(r#""content_base64": "KLUv/QBYOQAAY29udGVudA==""#));
+}
+
+#[test]
+fn exporting_content_with_encryption_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let export_path = fx.tmp_path("export.json.age");
+
+    let mut cmd = fx.cmd(["export", "--encrypt", "-o"]);
+    cmd.arg(&export_path);
+    cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+
+    // WHEN
+    let output = cmd
+        .output()
+        .expect("export command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let export = std::fs::read(&export_path).expect("export should've been read");
+    assert!(export.starts_with(b"age-encryption.org/v1"));
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_if_keys_dont_exist() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["export", "key", "absent-key", "-o"]);
+    cmd.arg(fx.tmp_path("export.tar"));
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't export content: keys don't exist in stash: ["absent-key"]
+    "#);
+}
+
+#[test]
+fn fails_if_no_entries_match_the_prefix() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["export", "--prefix", "api-", "-o"]);
+    cmd.arg(fx.tmp_path("export.tar"));
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't export content: no entries to export
+    ");
+}
@@ -0,0 +1,88 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn finding_duplicates_in_an_empty_stash_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["find-dupes"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no duplicates found
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn finding_duplicates_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let pushes = vec![
+        vec!["push", "key-c", "--data", "content"],
+        vec!["push", "key-a", "--data", "content"],
+        vec!["push", "key-b", "--data", "other content"],
+        vec!["push", "key-e", "--file-path", "tests/static/sample.txt"],
+        vec!["push", "key-d", "--file-path", "tests/static/sample.txt"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["find-dupes"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a key-c
+    key-d key-e
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn finding_duplicates_leaves_out_encrypted_content() {
+    // GIVEN
+    let fx = Fixture::new();
+    let pushes = vec![
+        vec!["push", "plain", "--data", "content"],
+        vec!["push", "encrypted", "--data", "content", "--encrypt"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["find-dupes"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no duplicates found
+
+    ----- stderr -----
+    ");
+}
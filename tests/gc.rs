@@ -0,0 +1,104 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn collecting_garbage_without_blobs_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["gc"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    nothing to remove
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn collecting_garbage_removes_unreferenced_blobs() {
+    // GIVEN
+    let fx = Fixture::new();
+    fx.write_config("dedupe = true");
+    let pushes = vec![
+        vec!["push", "key-a", "--data", "content"],
+        vec!["push", "key-b", "--data", "content"],
+        vec!["push", "key-c", "--data", "other content"],
+        vec!["push", "key-d", "--data", "more content"],
+        vec!["push", "key-d", "--data", "updated content"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let mut delete_cmd = fx.cmd(["delete", "key-a", "key-c"]);
+    delete_cmd
+        .output()
+        .expect("delete command should've been executed");
+
+    let mut cmd = fx.cmd(["gc"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    removed 2 unreferenced blobs (25 bytes)
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries_in(".blobs").len(), 2);
+    assert_eq!(fx.stashed_content("key-b"), b"content");
+    assert_eq!(fx.stashed_content("key-d"), b"updated content");
+}
+
+#[test]
+fn deduplicated_entries_survive_stash_encryption() {
+    // GIVEN
+    let fx = Fixture::new();
+    fx.write_config("dedupe = true");
+    for key in ["key-a", "key-b"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    for action in ["encrypt-stash", "decrypt-stash"] {
+        let mut cmd = fx.cmd([action]);
+        cmd.env("TASH_PASSPHRASE", "passphrase");
+        cmd.output().expect("command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["gc"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    nothing to remove
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries_in(".blobs").len(), 1);
+    assert_eq!(fx.stashed_content("key-a"), b"content");
+    assert_eq!(fx.stashed_content("key-b"), b"content");
+}
@@ -65,6 +65,336 @@ fn getting_content_and_popping_works() {
     ");
 }
 
+#[test]
+fn getting_templated_content_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd([
+        "push",
+        "key",
+        "--data",
+        "curl -H 'Authorization: {{token}}' https://{{ host }}/api",
+    ]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd([
+        "get",
+        "key",
+        "--var",
+        "host=example.com",
+        "--var",
+        "token=abc",
+    ]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    curl -H 'Authorization: abc' https://example.com/api
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn getting_templated_content_with_vars_from_env_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "ssh {{user}}@{{host}}"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "--var", "host=example.com", "--vars-from-env"]);
+    cmd.env("user", "admin");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    ssh admin@example.com
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn getting_json_content_pretty_printed_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--file-path", "tests/static/sample.json"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "--pretty"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "name": "tash",
+      "tags": [
+        "cli",
+        "stash"
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn getting_content_and_copying_it_to_clipboard_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--file-path", "tests/static/sample.txt"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "--clipboard", "--no-output"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+    assert_eq!(
+        fx.clipboard_content(),
+        include_bytes!("static/sample.txt").to_vec()
+    );
+}
+
+#[test]
+fn getting_an_image_and_copying_it_to_clipboard_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--file-path", "tests/static/sample.png"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "--clipboard", "--no-output"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+    assert_eq!(
+        fx.clipboard_content(),
+        include_bytes!("static/sample.png").to_vec()
+    );
+}
+
+#[test]
+fn getting_content_and_copying_it_to_both_selections_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "-nc", "--selection", "both"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.clipboard_content(), b"content".to_vec());
+    assert_eq!(fx.primary_selection_content(), b"content".to_vec());
+}
+
+#[test]
+fn clipboard_is_restored_after_the_provided_duration() {
+    // GIVEN
+    let fx = Fixture::new();
+    fx.set_clipboard_content("previous content");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret", "--sensitive"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "-nc", "--clear-after", "200ms"]);
+
+    // WHEN
+    cmd.output().expect("get command should've been executed");
+
+    // THEN
+    assert_eq!(fx.clipboard_content(), b"secret".to_vec());
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    assert_eq!(fx.clipboard_content(), b"previous content".to_vec());
+}
+
+#[test]
+fn clipboard_is_left_alone_if_it_changes_before_the_provided_duration() {
+    // GIVEN
+    let fx = Fixture::new();
+    fx.set_clipboard_content("previous content");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "-nc", "--clear-after", "500ms"]);
+
+    // WHEN
+    cmd.output().expect("get command should've been executed");
+    fx.set_clipboard_content("newer content");
+
+    // THEN
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    assert_eq!(fx.clipboard_content(), b"newer content".to_vec());
+}
+
+#[test]
+fn getting_content_encrypted_with_a_passphrase_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret content", "--encrypt"]);
+    push_cmd.env("TASH_PASSPHRASE", "passphrase");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    secret content
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn getting_content_encrypted_with_an_identity_file_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret content", "--encrypt"]);
+    push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+    cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    secret content
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn getting_encrypted_content_with_identity_file_from_config_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let identity_file = std::path::absolute("tests/static/identity.txt")
+        .expect("absolute path should've been computed");
+    fx.write_config(&format!("identity_file = {:?}", identity_file));
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret content", "--encrypt"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    secret content
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn getting_compressed_content_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "compressed content", "--compress"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    compressed content
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn getting_compressed_and_encrypted_content_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd([
+        "push",
+        "key",
+        "--data",
+        "secret content",
+        "--compress",
+        "--encrypt",
+    ]);
+    push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+    cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    secret content
+    ----- stderr -----
+    ");
+}
+
 //------------//
 //  FAILURES  //
 //------------//
@@ -86,3 +416,171 @@ fn fails_if_key_doesnt_exist() {
     Error: couldn't get content: key doesn't exist in stash
     ");
 }
+
+#[test]
+fn fails_to_copy_binary_content_without_outputting_it() {
+    // GIVEN
+    let fx = Fixture::new();
+    let binary_file = fx.tmp_path("binary");
+    std::fs::write(&binary_file, [0u8, 159, 146, 150]).expect("binary file should've been written");
+    let mut push_cmd = fx.cmd(["push", "key", "--file-path"]);
+    push_cmd.arg(&binary_file);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "-c"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: content of type application/octet-stream can't be copied to the system clipboard
+    ");
+}
+
+#[test]
+fn fails_if_template_vars_are_missing() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "ssh {{user}}@{{host}}:{{port}}"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key", "--var", "host=example.com"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: values for the following template variables weren't provided: user, port
+    ");
+}
+
+#[test]
+fn fails_if_passphrase_is_incorrect() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret content", "--encrypt"]);
+    push_cmd.env("TASH_PASSPHRASE", "passphrase");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+    cmd.env("TASH_PASSPHRASE", "incorrect");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: couldn't decrypt content: Decryption failed
+    ");
+}
+
+#[test]
+fn fails_if_identity_file_is_not_available_for_encrypted_content() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "secret content", "--encrypt"]);
+    push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: no identity file configured (set TASH_IDENTITY_FILE, or "identity_file" in the config)
+    "#);
+}
+
+#[test]
+fn fails_if_content_doesnt_match_its_checksum() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("key", "corrupted");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: content doesn't match the checksum recorded when it was stashed; it may be corrupted
+    ");
+}
+
+#[test]
+fn fails_if_encrypted_content_doesnt_match_its_checksum() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content", "--encrypt"]);
+    push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("key", "corrupted");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+    cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't get content: content doesn't match the checksum recorded when it was stashed; it may be corrupted
+    ");
+}
+
+#[test]
+fn fails_if_clear_after_duration_is_too_large() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["get", "key", "-c", "--clear-after", "18446744073709551615h"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid value '18446744073709551615h' for '--clear-after <DURATION>': is too large
+
+    For more information, try '--help'.
+    ");
+}
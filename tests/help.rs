@@ -23,12 +23,31 @@ fn shows_help() {
     Usage: tash <COMMAND>
 
     Commands:
-      delete  Delete one or more content items
-      empty   Empty entire stash
-      ls      List stashed content keys
-      get     Get content from stash
-      push    Stash content
-      help    Print this message or the help of the given subcommand(s)
+      append         Append content to an entry (creates the entry if it doesn't exist)
+      completions    Print a script that sets up shell completions (including keys in the stash)
+      daemon         Serve ls and get from an in-memory index over a Unix socket, for fast repeated access (eg. by editors)
+      decrypt-stash  Turn an encrypted stash back into a regular one
+      delete         Delete one or more content items
+      doctor         Check the stash for problems (eg. stray files, missing metadata, loose file permissions)
+      empty          Empty entire stash
+      encrypt-stash  Encrypt the entire stash (content and keys) with a passphrase
+      export         Export entries (content and metadata) to a tar archive or a JSON bundle
+      import         Import entries from an export, or from the files in a directory
+      ls             List stashed content keys
+      lock           Lock an encrypted stash that was unlocked earlier
+      find-dupes     List keys whose entries have identical content
+      gc             Remove stored content that no entry refers to anymore (when deduplication is enabled)
+      get            Get content from stash
+      man            Print the man page for tash, or write man pages for every command to a directory
+      mcp            Run a Model Context Protocol server over stdio, with tools for listing, searching, getting, and pushing entries
+      prepend        Prepend content to an entry (creates the entry if it doesn't exist)
+      push           Stash content
+      serve          Serve a local HTTP API for listing, getting, pushing, deleting, and searching entries
+      sync           Sync the stash with a git remote (set up via sync init), or with another data directory
+      run            Run stashed content as a shell command
+      unlock         Unlock an encrypted stash for a while, so that commands don't ask for its passphrase
+      vars           List template variables used in an entry
+      help           Print help for a command (eg. tash help sync init), or on a topic (keys, config, env, error-codes)
 
     Options:
       -h, --help  Print help
@@ -36,3 +55,194 @@ fn shows_help() {
     ----- stderr -----
     ");
 }
+
+#[test]
+fn help_lists_topics() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["help"]);
+
+    // WHEN
+    let output = cmd.output().expect("help command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let help = String::from_utf8_lossy(&output.stdout);
+    let topics = help
+        .find("Help topics")
+        .map(|i| &help[i..])
+        .unwrap_or_default();
+    insta::assert_snapshot!(topics, @r"
+    Help topics (tash help <TOPIC>):
+      keys           Rules keys need to follow
+      config         Settings tash's config file supports
+      env            Environment variables tash reads
+      error-codes    What the codes reported with unexpected errors mean
+    ");
+}
+
+#[test]
+fn help_for_a_command_is_the_same_as_its_help_flag() {
+    // GIVEN
+    let fx = Fixture::new();
+
+    for command in [vec!["get"], vec!["sync", "init"]] {
+        let mut help_cmd = fx.cmd(["help"]);
+        help_cmd.args(&command);
+        let mut flag_cmd = fx.cmd(&command);
+        flag_cmd.arg("--help");
+
+        // WHEN
+        let help_output = help_cmd
+            .output()
+            .expect("help command should've been executed");
+        let flag_output = flag_cmd.output().expect("command should've been executed");
+
+        // THEN
+        assert!(help_output.status.success());
+        assert_eq!(help_output.stdout, flag_output.stdout);
+    }
+}
+
+#[test]
+fn shows_help_on_a_topic() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["help", "keys"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Every entry in the stash is stored under a key. Keys need to conform to the
+    regex ^[a-z0-9_-]{1,30}$, ie. they're 1 to 30 characters long, and only
+    contain lowercase letters, digits, underscores, and hyphens.
+
+      tash push db-url --data postgres://localhost
+      tash get db-url
+
+    Keys are used as file names in the data directory, which is why they're this
+    restrictive. Files in it that aren't named after valid keys aren't treated as
+    entries ("tash doctor" reports them).
+
+    Pushing content under a key that already exists replaces the entry's content;
+    "push --prevent-overwrite" refuses to do so instead.
+
+    "import" turns file names into valid keys when importing a directory (eg.
+    "My Notes.txt" becomes my-notes-txt), and "sync --dir" keeps versions of
+    entries that conflict as KEY.conflict-<TIMESTAMP> (with their metadata in
+    .meta/KEY.conflict-<TIMESTAMP>.json), which aren't valid keys (and so aren't
+    entries) until they're renamed, along with their metadata.
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn shows_man_page() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["man"]);
+
+    // WHEN
+    let output = cmd.output().expect("man command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let sections = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| l.starts_with(".SH"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(sections, @r#"
+    .SH NAME
+    .SH SYNOPSIS
+    .SH DESCRIPTION
+    .SH OPTIONS
+    .SH SUBCOMMANDS
+    .SH KEYS
+    .SH CONFIGURATION
+    .SH ENVIRONMENT
+    .SH "ERROR CODES"
+    "#);
+}
+
+#[test]
+fn writes_man_pages_for_every_command() {
+    // GIVEN
+    let fx = Fixture::new();
+    let dir = fx.tmp_path("man");
+    let mut cmd = fx.cmd(["man", "--dir"]);
+    cmd.arg(&dir);
+
+    // WHEN
+    let output = cmd.output().expect("man command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let mut pages = std::fs::read_dir(&dir)
+        .expect("man directory should've been created")
+        .map(|e| {
+            e.expect("entry should've been readable")
+                .file_name()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    pages.sort();
+    insta::assert_debug_snapshot!(pages, @r#"
+    [
+        "tash-append.1",
+        "tash-completions.1",
+        "tash-daemon.1",
+        "tash-decrypt-stash.1",
+        "tash-delete.1",
+        "tash-doctor.1",
+        "tash-empty.1",
+        "tash-encrypt-stash.1",
+        "tash-export.1",
+        "tash-find-dupes.1",
+        "tash-gc.1",
+        "tash-get.1",
+        "tash-help.1",
+        "tash-import.1",
+        "tash-lock.1",
+        "tash-ls.1",
+        "tash-man.1",
+        "tash-mcp.1",
+        "tash-prepend.1",
+        "tash-push.1",
+        "tash-run.1",
+        "tash-serve.1",
+        "tash-sync-init.1",
+        "tash-sync.1",
+        "tash-unlock.1",
+        "tash-vars.1",
+        "tash.1",
+    ]
+    "#);
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn help_fails_for_an_unknown_topic() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["help", "nope"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: there's no help topic or command called "nope" (topics: keys, config, env, error-codes)
+    "#);
+}
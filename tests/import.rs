@@ -0,0 +1,304 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn importing_from_an_export_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let pushes = vec![
+        vec!["push", "key-a", "--data", "content", "--sensitive"],
+        vec!["push", "key-b", "--data", "[1, 2, 3]", "--compress"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let export_path = fx.tmp_path("export.tar");
+    let mut export_cmd = fx.cmd(["export", "-o"]);
+    export_cmd.arg(&export_path);
+    export_cmd
+        .output()
+        .expect("export command should've been executed");
+    let mut empty_cmd = fx.cmd(["empty", "-y"]);
+    empty_cmd
+        .output()
+        .expect("empty command should've been executed");
+
+    let mut cmd = fx.cmd(["import", "-y"]);
+    cmd.arg(&export_path);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    add key-a
+    add key-b
+
+    2 to add, 0 to overwrite, 0 to skip
+    imported 2 entries
+
+    ----- stderr -----
+    ");
+    let mut ls_cmd = fx.cmd(["ls", "-l"]);
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a              7  text/plain        sensitive
+    key-b  9 (18 stored)  application/json  compressed
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn importing_from_an_encrypted_json_export_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let export_path = fx.tmp_path("export.json.age");
+    let mut export_cmd = fx.cmd(["export", "--encrypt", "-o"]);
+    export_cmd.arg(&export_path);
+    export_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+    export_cmd
+        .output()
+        .expect("export command should've been executed");
+    let mut delete_cmd = fx.cmd(["delete", "key"]);
+    delete_cmd
+        .output()
+        .expect("delete command should've been executed");
+
+    let mut cmd = fx.cmd(["import", "-y"]);
+    cmd.arg(&export_path);
+    cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+
+    // WHEN
+    let output = cmd
+        .output()
+        .expect("import command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    assert_eq!(fx.stashed_content("key"), b"content");
+}
+
+#[test]
+fn importing_from_a_directory_turns_files_into_entries() {
+    // GIVEN
+    let fx = Fixture::new();
+    let dir = fx.tmp_path("snippets");
+    std::fs::create_dir(&dir).expect("directory should've been created");
+    for (name, content) in [
+        ("deploy.sh", "./deploy.sh --prod"),
+        ("My Notes.TXT", "some notes"),
+        ("config.json", r#"{"a": 1}"#),
+        (".hidden", "hidden"),
+        ("???", "content"),
+    ] {
+        std::fs::write(dir.join(name), content).expect("file should've been written to");
+    }
+
+    let mut cmd = fx.cmd(["import", "-y"]);
+    cmd.arg(&dir);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    skip ??? (no valid key can be made from its name)
+    add my-notes-txt (from "My Notes.TXT")
+    add config-json (from "config.json")
+    add deploy-sh (from "deploy.sh")
+
+    3 to add, 0 to overwrite, 1 to skip
+    imported 3 entries
+
+    ----- stderr -----
+    "#);
+    let mut ls_cmd = fx.cmd(["ls", "-l"]);
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    config-json    8  application/json
+    deploy-sh     18  text/plain
+    my-notes-txt  10  text/plain
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn importing_with_conflicts_can_skip_overwrite_or_rename_entries() {
+    // GIVEN
+    let fx = Fixture::new();
+    for key in ["skipped", "overwritten", "renamed", "renamed-1"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "old content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let dir = fx.tmp_path("snippets");
+    std::fs::create_dir(&dir).expect("directory should've been created");
+    for name in ["skipped", "overwritten", "renamed"] {
+        std::fs::write(dir.join(name), "new content").expect("file should've been written to");
+    }
+
+    let strategies = [
+        ("skip", "skipped"),
+        ("overwrite", "overwritten"),
+        ("rename", "renamed"),
+    ];
+    for (strategy, name) in strategies {
+        let source_dir = fx.tmp_path(strategy);
+        std::fs::create_dir(&source_dir).expect("directory should've been created");
+        std::fs::copy(dir.join(name), source_dir.join(name)).expect("file should've been copied");
+        let mut cmd = fx.cmd(["import", "-y", "--on-conflict", strategy]);
+        cmd.arg(&source_dir);
+        cmd.output()
+            .expect("import command should've been executed");
+    }
+
+    // WHEN
+    // THEN
+    assert_eq!(fx.stashed_content("skipped"), b"old content");
+    assert_eq!(fx.stashed_content("overwritten"), b"new content");
+    assert_eq!(fx.stashed_content("renamed"), b"old content");
+    assert_eq!(fx.stashed_content("renamed-1"), b"old content");
+    assert_eq!(fx.stashed_content("renamed-2"), b"new content");
+}
+
+#[test]
+fn importing_with_a_dry_run_doesnt_change_the_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "notes", "--data", "old content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let dir = fx.tmp_path("snippets");
+    std::fs::create_dir(&dir).expect("directory should've been created");
+    for name in ["notes", "todo"] {
+        std::fs::write(dir.join(name), "new content").expect("file should've been written to");
+    }
+
+    let mut cmd = fx.cmd(["import", "--dry-run", "--on-conflict", "overwrite"]);
+    cmd.arg(&dir);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    overwrite notes
+    add todo
+
+    1 to add, 1 to overwrite, 0 to skip
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries(), vec![".meta", "notes"]);
+    assert_eq!(fx.stashed_content("notes"), b"old content");
+}
+
+#[test]
+fn importing_can_be_cancelled() {
+    // GIVEN
+    let fx = Fixture::new();
+    let dir = fx.tmp_path("snippets");
+    std::fs::create_dir(&dir).expect("directory should've been created");
+    std::fs::write(dir.join("notes"), "content").expect("file should've been written to");
+
+    let mut cmd = fx.cmd(["import"]);
+    cmd.arg(&dir);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin("no\n"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    add notes
+
+    1 to add, 0 to overwrite, 0 to skip
+    Enter "yes" to continue.
+    import cancelled
+
+    ----- stderr -----
+    "#);
+    assert!(fx.data_dir_entries().is_empty());
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_if_keys_conflict_by_default() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "notes", "--data", "old content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let dir = fx.tmp_path("snippets");
+    std::fs::create_dir(&dir).expect("directory should've been created");
+    for name in ["notes", "todo"] {
+        std::fs::write(dir.join(name), "new content").expect("file should've been written to");
+    }
+
+    let mut cmd = fx.cmd(["import", "-y"]);
+    cmd.arg(&dir);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    conflict notes (already exists)
+    add todo
+
+    ----- stderr -----
+    Error: couldn't import content: keys already exist in the stash: ["notes"] (use --on-conflict to skip, overwrite, or rename them)
+    "#);
+    assert_eq!(fx.data_dir_entries(), vec![".meta", "notes"]);
+}
+
+#[test]
+fn fails_if_json_bundle_has_an_unsupported_version() {
+    // GIVEN
+    let fx = Fixture::new();
+    let bundle_path = fx.tmp_path("bundle.json");
+    std::fs::write(&bundle_path, r#"{"version": 2, "entries": []}"#)
+        .expect("bundle should've been written to");
+
+    let mut cmd = fx.cmd(["import", "-y"]);
+    cmd.arg(&bundle_path);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't import content: JSON bundle has version 2, which this version of tash doesn't support
+    ");
+}
@@ -50,3 +50,185 @@ fn listing_content_works() {
     ----- stderr -----
     ");
 }
+
+#[test]
+fn listing_content_skips_files_that_arent_entries() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("notes.txt", "content");
+
+    let mut cmd = fx.cmd(["ls"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn listing_content_with_details_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let pushes = vec![
+        vec!["push", "text", "--file-path", "tests/static/sample.txt"],
+        vec!["push", "json", "--file-path", "tests/static/sample.json"],
+        vec!["push", "yaml", "--data=---\nname: tash"],
+        vec!["push", "json-from-flag", "--data", "[1, 2, 3]"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["ls", "--long"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    json            43  application/json
+    json-from-flag   9  application/json
+    text            44  text/plain
+    yaml            14  application/yaml
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn listing_content_with_checksums_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    for key in ["key-a", "key-b"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["ls", "--long", "--hash"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a  7  ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73  text/plain
+    key-b  7  ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73  text/plain
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn checksums_of_encrypted_entries_dont_reveal_their_content() {
+    // GIVEN
+    let fx = Fixture::new();
+    let pushes = vec![
+        vec!["push", "plain", "--data", "content"],
+        vec!["push", "encrypted", "--data", "content", "--encrypt"],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["ls", "--long", "--hash"]);
+
+    // WHEN
+    let output = cmd.output().expect("ls command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let plaintext_digest = "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73";
+    assert_eq!(stdout.matches(plaintext_digest).count(), 1, "{stdout}");
+    let encrypted_line = stdout
+        .lines()
+        .find(|l| l.starts_with("encrypted"))
+        .expect("encrypted entry should've been listed");
+    assert!(!encrypted_line.contains("  7  "), "{encrypted_line}");
+}
+
+#[test]
+fn listing_content_with_details_shows_compressed_sizes() {
+    // GIVEN
+    let fx = Fixture::new();
+    let content = "GET /health 200\n".repeat(64);
+    let pushes = vec![
+        vec!["push", "compressed", "--data", &content, "--compress"],
+        vec!["push", "uncompressed", "--data", &content],
+    ];
+    for args in pushes {
+        let mut push_cmd = fx.cmd(args);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["ls", "--long"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    compressed    1024 (32 stored)  text/plain  compressed
+    uncompressed              1024  text/plain
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn listing_content_with_details_shows_flags() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd([
+        "push",
+        "key",
+        "--data",
+        "content",
+        "--sensitive",
+        "--encrypt",
+    ]);
+    push_cmd.env("TASH_IDENTITY_FILE", "tests/static/identity.txt");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["ls", "--long"]);
+
+    // WHEN
+    // THEN
+    // encrypted content's size varies, since age adds random stanzas to its header
+    insta::with_settings!({filters => vec![(r"key  \d+", "key  [SIZE]")]}, {
+        assert_cmd_snapshot!(cmd, @r"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        key  [SIZE]  text/plain  sensitive,encrypted
+
+        ----- stderr -----
+        ");
+    });
+}
@@ -0,0 +1,315 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+use std::io::Write;
+use std::process::Stdio;
+
+fn tool_call(id: u32, name: &str, arguments: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{id},"method":"tools/call","params":{{"name":"{name}","arguments":{arguments}}}}}"#
+    )
+}
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn initializing_and_pinging_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["mcp"]);
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","capabilities":{},"clientInfo":{"name":"test","version":"1.0"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#,
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    insta::with_settings!({filters => vec![(r#""version":"[^"]+""#, r#""version":"[VERSION]""#)]}, {
+        assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        {"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"tash","version":"[VERSION]"}}}
+        {"jsonrpc":"2.0","id":2,"result":{}}
+
+        ----- stderr -----
+        "#);
+    });
+}
+
+#[test]
+fn tools_list_includes_all_tools() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["mcp"]);
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
+
+    // WHEN
+    let mut child = cmd.spawn().expect("mcp command should've been spawned");
+    child
+        .stdin
+        .take()
+        .expect("stdin should've been piped")
+        .write_all(br#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+        .expect("message should've been written");
+    let output = child
+        .wait_with_output()
+        .expect("mcp command should've been executed");
+
+    // THEN
+    let response: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("response should've been JSON");
+    let names = response["result"]["tools"]
+        .as_array()
+        .expect("tools should've been an array")
+        .iter()
+        .map(|t| t["name"].as_str().unwrap_or_default().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["list_entries", "search_entries", "get_entry", "push_entry"]
+    );
+}
+
+#[test]
+fn pushing_listing_and_getting_entries_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["mcp"]);
+    let messages = [
+        tool_call(
+            1,
+            "push_entry",
+            r#"{"key":"key-a","content":"content for key-a"}"#,
+        ),
+        tool_call(2, "list_entries", "{}"),
+        tool_call(3, "get_entry", r#"{"key":"key-a"}"#),
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"stashed 17 bytes under \"key-a\""}],"isError":false}}
+    {"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"[\n  {\n    \"key\": \"key-a\",\n    \"size\": 17,\n    \"stored_size\": 17,\n    \"content_type\": \"text/plain\",\n    \"sensitive\": false,\n    \"encrypted\": false,\n    \"compressed\": false\n  }\n]"}],"isError":false}}
+    {"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"content for key-a"}],"isError":false}}
+
+    ----- stderr -----
+    "#);
+    assert_eq!(fx.stashed_content("key-a"), b"content for key-a");
+}
+
+#[test]
+fn searching_entries_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "notes", "--data", "the DB runs on port 5432"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut push_cmd = fx.cmd(["push", "other", "--data", "nothing to see here"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut cmd = fx.cmd(["mcp"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(tool_call(1, "search_entries", r#"{"query":"db"}"#)), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"[\n  {\n    \"key\": \"notes\",\n    \"size\": 24,\n    \"stored_size\": 24,\n    \"content_type\": \"text/plain\",\n    \"sensitive\": false,\n    \"encrypted\": false,\n    \"compressed\": false\n  }\n]"}],"isError":false}}
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn sensitive_content_is_handed_out_if_allowed() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "token", "--data", "hunter2", "--sensitive"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut cmd = fx.cmd(["mcp", "--allow-sensitive"]);
+    let messages = [
+        tool_call(1, "get_entry", r#"{"key":"token"}"#),
+        tool_call(2, "search_entries", r#"{"query":"hunter"}"#),
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"hunter2"}],"isError":false}}
+    {"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"[\n  {\n    \"key\": \"token\",\n    \"size\": 7,\n    \"stored_size\": 7,\n    \"content_type\": \"text/plain\",\n    \"sensitive\": true,\n    \"encrypted\": false,\n    \"compressed\": false\n  }\n]"}],"isError":false}}
+
+    ----- stderr -----
+    "#);
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn sensitive_content_is_withheld_by_default() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "token", "--data", "hunter2", "--sensitive"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut cmd = fx.cmd(["mcp"]);
+    let messages = [
+        tool_call(1, "get_entry", r#"{"key":"token"}"#),
+        tool_call(2, "search_entries", r#"{"query":"hunter"}"#),
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"\"token\" is sensitive or encrypted; its content is only handed out if the server is started with --allow-sensitive"}],"isError":true}}
+    {"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"[]"}],"isError":false}}
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn sensitive_entries_arent_overwritten_by_default() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "token", "--data", "hunter2", "--sensitive"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut cmd = fx.cmd(["mcp"]);
+    let messages = tool_call(
+        1,
+        "push_entry",
+        r#"{"key":"token","content":"new content"}"#,
+    );
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"\"token\" is sensitive or encrypted; it can only be overwritten if the server is started with --allow-sensitive"}],"isError":true}}
+
+    ----- stderr -----
+    "#);
+    assert_eq!(fx.stashed_content("token"), b"hunter2");
+}
+
+#[test]
+fn tool_failures_are_reported_as_results() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key-a", "--data", "content for key-a"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut cmd = fx.cmd(["mcp"]);
+    let messages = [
+        tool_call(1, "get_entry", r#"{"key":"absent"}"#),
+        tool_call(
+            2,
+            "push_entry",
+            r#"{"key":"key-a","content":"new content","prevent_overwrite":true}"#,
+        ),
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"couldn't get content: key doesn't exist in stash"}],"isError":true}}
+    {"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"couldn't push content: key already exists in the stash"}],"isError":true}}
+
+    ----- stderr -----
+    "#);
+    assert_eq!(fx.stashed_content("key-a"), b"content for key-a");
+}
+
+#[test]
+fn malformed_messages_get_json_rpc_errors() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["mcp"]);
+    let messages = [
+        "not json".to_string(),
+        r#"{"jsonrpc":"2.0","id":1,"method":"resources/list"}"#.to_string(),
+        tool_call(2, "delete_entry", "{}"),
+        tool_call(3, "get_entry", "{}"),
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"expected ident at line 1 column 2"}}
+    {"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"method not found: resources/list"}}
+    {"jsonrpc":"2.0","id":2,"error":{"code":-32602,"message":"unknown tool: delete_entry"}}
+    {"jsonrpc":"2.0","id":3,"error":{"code":-32602,"message":"invalid params: missing field `key`"}}
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn encrypted_content_fails_instead_of_prompting_for_a_passphrase() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "token", "--data", "hunter2", "--encrypt"]);
+    push_cmd.env("TASH_PASSPHRASE", "passphrase");
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut cmd = fx.cmd(["mcp", "--allow-sensitive"]);
+    let messages = [
+        tool_call(1, "get_entry", r#"{"key":"token"}"#),
+        tool_call(
+            2,
+            "push_entry",
+            r#"{"key":"other","content":"content","encrypt":true}"#,
+        ),
+    ]
+    .join("\n");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin(messages), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"couldn't get content: can't prompt for a passphrase here; set TASH_PASSPHRASE instead"}],"isError":true}}
+    {"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"couldn't push content: can't prompt for a passphrase here; set TASH_PASSPHRASE instead"}],"isError":true}}
+
+    ----- stderr -----
+    "#);
+}
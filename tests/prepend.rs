@@ -0,0 +1,72 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn prepending_to_existing_content_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "second line"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut prepend_cmd = fx.cmd([
+        "prepend",
+        "key",
+        "--data",
+        "first line",
+        "--separator",
+        "\n",
+    ]);
+    prepend_cmd
+        .output()
+        .expect("prepend command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    first line
+    second line
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn prepending_from_a_file_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "trailing content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut prepend_cmd = fx.cmd(["prepend", "key", "--file-path", "tests/static/sample.txt"]);
+    prepend_cmd
+        .output()
+        .expect("prepend command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    A sample file for tash.
+
+    Content goes here.
+    trailing content
+    ----- stderr -----
+    ");
+}
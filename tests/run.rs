@@ -0,0 +1,179 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn running_content_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo \"hello, $1 and $2\""]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["run", "key", "--yes", "--", "alice", "bob"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello, alice and bob
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn running_content_after_confirmation_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo hello"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["run", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin("yes\n"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello
+
+    ----- stderr -----
+    This will run the following command using "sh":
+
+    echo hello
+
+    Enter "yes" to continue.
+    "#);
+}
+
+#[test]
+fn running_content_is_cancelled_if_not_confirmed() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo hello"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["run", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd.pass_stdin("no\n"), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    This will run the following command using "sh":
+
+    echo hello
+
+    Enter "yes" to continue.
+    run cancelled
+    "#);
+}
+
+#[test]
+fn running_templated_content_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo {{greeting}}, world"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["run", "key", "--yes", "--var", "greeting=hello"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello, world
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn running_content_passes_exit_code_through() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo failing >&2; exit 3"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["run", "key", "--yes"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+
+    ----- stderr -----
+    failing
+    ");
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_if_key_doesnt_exist() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["run", "non-existent-key", "--yes"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't run content: key doesn't exist in stash
+    ");
+}
+
+#[test]
+fn fails_if_content_doesnt_match_its_checksum() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("key", "echo corrupted");
+
+    let mut cmd = fx.cmd(["run", "key", "--yes"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't run content: content doesn't match the checksum recorded when it was stashed; it may be corrupted
+    ");
+    assert_eq!(fx.stashed_content("key"), b"echo corrupted");
+}
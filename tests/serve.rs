@@ -0,0 +1,318 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+const TOKEN: &str = "test-token";
+
+fn fixture_with_token() -> Fixture {
+    let fx = Fixture::new();
+    fx.write_config(&format!("serve_token = \"{TOKEN}\"\n"));
+    fx
+}
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn pushing_and_getting_entries_works() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (push_status, push_body) =
+        server.request("PUT", "/entries/key-a", Some(TOKEN), b"content for key-a");
+    let (overwrite_status, _) =
+        server.request("PUT", "/entries/key-a", Some(TOKEN), b"new content");
+    let (get_status, get_body) = server.request("GET", "/entries/key-a", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(push_status, 201);
+    assert_eq!(push_body, "");
+    assert_eq!(overwrite_status, 204);
+    assert_eq!(get_status, 200);
+    assert_eq!(get_body, "new content");
+    assert_eq!(fx.stashed_content("key-a"), b"new content");
+}
+
+#[test]
+fn pushing_applies_flags_from_the_query_string() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (status, _) = server.request(
+        "PUT",
+        "/entries/key-a?sensitive&compress=true&encrypt=false",
+        Some(TOKEN),
+        b"content for key-a",
+    );
+
+    // THEN
+    assert_eq!(status, 201);
+    let mut ls_cmd = fx.cmd(["ls", "--long"]);
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-a  17 (26 stored)  text/plain  sensitive,compressed
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn listing_entries_returns_their_details() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let mut push_cmd = fx.cmd([
+        "push",
+        "key-a",
+        "--data",
+        "content for key-a",
+        "--sensitive",
+    ]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("legacy", "stashed by an older version");
+    let server = fx.serve();
+
+    // WHEN
+    let (status, body) = server.request("GET", "/entries", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(status, 200);
+    insta::assert_snapshot!(body, @r#"
+    [{"key":"key-a","size":17,"stored_size":17,"sha256":"b6855fab390262082c616af66651639ff69580a7f285ab6f1cb45815124490a5","content_type":"text/plain","sensitive":true,"encrypted":false,"compressed":false},{"key":"legacy","size":null,"stored_size":27,"sha256":"c3f819774eb0b9920b8c1dd64bd3910a82a78cf83f3c5f6b2c41c42982291c0f","content_type":null,"sensitive":false,"encrypted":false,"compressed":false}]
+    "#);
+}
+
+#[test]
+fn deleting_an_entry_works() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+    server.request("PUT", "/entries/key-a", Some(TOKEN), b"content for key-a");
+    server.request("PUT", "/entries/key-b", Some(TOKEN), b"content for key-b");
+
+    // WHEN
+    let (status, body) = server.request("DELETE", "/entries/key-a", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(status, 204);
+    assert_eq!(body, "");
+    assert_eq!(fx.data_dir_entries(), vec![".meta", "key-b"]);
+}
+
+#[test]
+fn searching_matches_keys_and_text_content() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+    server.request(
+        "PUT",
+        "/entries/db-url",
+        Some(TOKEN),
+        b"postgres://localhost",
+    );
+    server.request(
+        "PUT",
+        "/entries/notes",
+        Some(TOKEN),
+        b"the DB runs on port 5432",
+    );
+    server.request("PUT", "/entries/other", Some(TOKEN), b"nothing to see here");
+
+    // WHEN
+    let (status, body) = server.request("GET", "/search?q=DB", Some(TOKEN), b"");
+    let (phrase_status, phrase_body) =
+        server.request("GET", "/search?q=runs+on%20port", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(status, 200);
+    let keys = |body: &str| {
+        serde_json::from_str::<Vec<serde_json::Value>>(body)
+            .expect("body should've been a JSON array")
+            .into_iter()
+            .map(|e| e["key"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&body), vec!["db-url", "notes"]);
+    assert_eq!(phrase_status, 200);
+    assert_eq!(keys(&phrase_body), vec!["notes"]);
+}
+
+#[test]
+fn changes_made_via_the_api_are_committed_for_sync() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let remote = fx.create_remote("remote.git");
+    let mut init_cmd = fx.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+    let server = fx.serve();
+
+    // WHEN
+    server.request("PUT", "/entries/key-a", Some(TOKEN), b"content for key-a");
+    server.request("GET", "/entries/key-a", Some(TOKEN), b"");
+    server.request("DELETE", "/entries/key-a", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(fx.commit_subjects(), vec!["update key-a", "update key-a"]);
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_to_start_without_a_token() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["serve", "--bind", "127.0.0.1:0"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't serve API: no token is configured for the API; set "serve_token" in tash's config
+    "#);
+}
+
+#[test]
+fn requests_without_the_right_token_are_rejected() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (status, body) = server.request("GET", "/entries", None, b"");
+    let (wrong_token_status, _) = server.request("GET", "/entries", Some("wrong-token"), b"");
+
+    // THEN
+    assert_eq!(status, 401);
+    assert_eq!(
+        body,
+        r#"{"error":"missing or invalid bearer token","code":null}"#
+    );
+    assert_eq!(wrong_token_status, 401);
+}
+
+#[test]
+fn getting_a_nonexistent_entry_fails() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (status, body) = server.request("GET", "/entries/absent", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(status, 404);
+    assert_eq!(
+        body,
+        r#"{"error":"couldn't get content: key doesn't exist in stash","code":null}"#
+    );
+}
+
+#[test]
+fn pushing_with_prevent_overwrite_fails_if_the_key_exists() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+    server.request("PUT", "/entries/key-a", Some(TOKEN), b"content for key-a");
+
+    // WHEN
+    let (status, body) = server.request(
+        "PUT",
+        "/entries/key-a?prevent-overwrite",
+        Some(TOKEN),
+        b"new content",
+    );
+
+    // THEN
+    assert_eq!(status, 409);
+    assert_eq!(
+        body,
+        r#"{"error":"couldn't push content: key already exists in the stash","code":null}"#
+    );
+    assert_eq!(fx.stashed_content("key-a"), b"content for key-a");
+}
+
+#[test]
+fn pushing_to_an_invalid_key_fails() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (status, body) = server.request("PUT", "/entries/Invalid.Key", Some(TOKEN), b"content");
+
+    // THEN
+    assert_eq!(status, 400);
+    assert_eq!(
+        body,
+        r#"{"error":"couldn't push content: incorrect key provided (valid regex: ^[a-z0-9_-]{1,30}$)","code":null}"#
+    );
+}
+
+#[test]
+fn unknown_routes_and_methods_are_rejected() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (unknown_status, unknown_body) = server.request("GET", "/unknown", Some(TOKEN), b"");
+    let (method_status, _) = server.request("POST", "/entries", Some(TOKEN), b"");
+
+    // THEN
+    assert_eq!(unknown_status, 404);
+    assert_eq!(unknown_body, r#"{"error":"not found","code":null}"#);
+    assert_eq!(method_status, 405);
+}
+
+#[test]
+fn pushing_content_thats_too_large_is_rejected() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+    let content = vec![b'a'; 50 * 1024 * 1024 + 1];
+
+    // WHEN
+    let (status, body) = server.request("PUT", "/entries/key-a", Some(TOKEN), &content);
+
+    // THEN
+    assert_eq!(status, 413);
+    assert_eq!(
+        body,
+        r#"{"error":"couldn't push content: content is too large (actual: 52428801 bytes, threshold: 52428800 bytes)","code":null}"#
+    );
+    assert!(fx.data_dir_entries().is_empty());
+}
+
+#[test]
+fn encrypting_content_fails_instead_of_prompting_for_a_passphrase() {
+    // GIVEN
+    let fx = fixture_with_token();
+    let server = fx.serve();
+
+    // WHEN
+    let (status, body) = server.request("PUT", "/entries/key-a?encrypt", Some(TOKEN), b"content");
+
+    // THEN
+    assert_eq!(status, 400);
+    assert_eq!(
+        body,
+        r#"{"error":"couldn't push content: can't prompt for a passphrase here; set TASH_PASSPHRASE instead","code":null}"#
+    );
+}
@@ -0,0 +1,482 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+/// Checks `condition` every 50ms, for up to 10 seconds; returns whether it was met.
+fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    false
+}
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn encrypting_the_stash_hides_its_keys() {
+    // GIVEN
+    let fx = Fixture::new();
+    for key in ["first", "second"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["encrypt-stash"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    let output = cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    assert_eq!(fx.data_dir_entries(), vec!["stash.age", "stash.key"]);
+}
+
+#[test]
+fn encrypted_stash_can_be_used_with_its_passphrase() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut cmd = fx.cmd(["get", "key"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    content
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn unlocked_stash_doesnt_need_a_passphrase() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "first", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+    let mut unlock_cmd = fx.cmd(["unlock"]);
+    unlock_cmd.env("TASH_PASSPHRASE", "passphrase");
+    unlock_cmd
+        .output()
+        .expect("unlock command should've been executed");
+    let mut push_cmd = fx.cmd(["push", "second", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["ls"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    first
+    second
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries(), vec!["stash.age", "stash.key"]);
+}
+
+#[test]
+fn decrypting_the_stash_restores_its_entries() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    for action in ["encrypt-stash", "decrypt-stash"] {
+        let mut cmd = fx.cmd([action]);
+        cmd.env("TASH_PASSPHRASE", "passphrase");
+        cmd.output().expect("command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["ls", "--long"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key  7  text/plain
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries(), vec![".meta", "key"]);
+}
+
+#[test]
+fn unlocked_session_is_removed_once_it_expires() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+    let mut cmd = fx.cmd(["unlock", "--timeout", "1s"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    let output = cmd
+        .output()
+        .expect("unlock command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    let has_session = || fx.runtime_dir_entries().iter().any(|e| e.ends_with(".key"));
+    assert!(has_session());
+    assert!(wait_until(|| !has_session()), "session wasn't removed");
+}
+
+#[test]
+fn working_directory_of_a_killed_command_is_removed_by_the_next_one() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+    let has_work_dir = || {
+        fx.runtime_dir_entries()
+            .iter()
+            .any(|e| e.contains(".work-"))
+    };
+
+    // "empty" keeps the stash open while it waits for confirmation
+    let mut empty_cmd = fx.cmd(["empty"]);
+    empty_cmd
+        .env("TASH_PASSPHRASE", "passphrase")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let mut empty_child = empty_cmd
+        .spawn()
+        .expect("empty command should've been spawned");
+    assert!(wait_until(has_work_dir), "working directory wasn't created");
+    empty_child
+        .kill()
+        .expect("empty command should've been killed");
+    empty_child.wait().expect("empty command should've exited");
+    assert!(has_work_dir());
+
+    let mut cmd = fx.cmd(["ls"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    let output = cmd.output().expect("ls command should've been executed");
+
+    // THEN
+    assert!(output.status.success());
+    assert!(!has_work_dir());
+}
+
+#[test]
+fn commands_using_an_encrypted_stash_wait_for_each_other() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "first", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    // "empty" asks for confirmation while the stash is open
+    let mut empty_cmd = fx.cmd(["empty"]);
+    empty_cmd
+        .env("TASH_PASSPHRASE", "passphrase")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let mut empty_child = empty_cmd
+        .spawn()
+        .expect("empty command should've been spawned");
+    assert!(
+        wait_until(|| fx
+            .runtime_dir_entries()
+            .iter()
+            .any(|e| e.contains(".work-"))),
+        "working directory wasn't created"
+    );
+
+    let mut push_cmd = fx.cmd(["push", "second", "--data", "content"]);
+    push_cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    let mut push_child = push_cmd
+        .spawn()
+        .expect("push command should've been spawned");
+    std::thread::sleep(Duration::from_millis(500));
+    let push_waited = push_child
+        .try_wait()
+        .expect("push command's status should've been checked")
+        .is_none();
+    empty_child
+        .stdin
+        .take()
+        .expect("stdin should've been piped")
+        .write_all(b"no\n")
+        .expect("confirmation should've been written");
+    empty_child.wait().expect("empty command should've exited");
+    let push_status = push_child.wait().expect("push command should've exited");
+
+    // THEN
+    assert!(push_waited, "push didn't wait for empty to be done");
+    assert!(push_status.success());
+    let mut ls_cmd = fx.cmd(["ls"]);
+    ls_cmd.env("TASH_PASSPHRASE", "passphrase");
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    first
+    second
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn stashed_commands_can_use_an_encrypted_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "token", "--data", "hunter2"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut push_cmd = fx.cmd(["push", "cmd", "--data", r#""$TASH_BIN" get token"#]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut run_cmd = fx.cmd(["run", "cmd", "--yes"]);
+    run_cmd
+        .env("TASH_PASSPHRASE", "passphrase")
+        .env("TASH_BIN", env!("CARGO_BIN_EXE_tash"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+
+    // WHEN
+    let mut run_child = run_cmd.spawn().expect("run command should've been spawned");
+    let exited = wait_until(|| matches!(run_child.try_wait(), Ok(Some(_))));
+    if !exited {
+        let _ = run_child.kill();
+    }
+    let output = run_child
+        .wait_with_output()
+        .expect("run command's output should've been read");
+
+    // THEN
+    assert!(exited, "run didn't exit; the stashed command waited on it");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hunter2");
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_if_passphrase_for_encrypted_stash_is_incorrect() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut cmd = fx.cmd(["ls"]);
+    cmd.env("TASH_PASSPHRASE", "incorrect");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't open stash: couldn't decrypt the stash's key: couldn't decrypt content: Decryption failed
+    ");
+}
+
+#[test]
+fn fails_if_stash_was_locked_again() {
+    // GIVEN
+    let fx = Fixture::new();
+    for action in ["encrypt-stash", "unlock"] {
+        let mut cmd = fx.cmd([action]);
+        cmd.env("TASH_PASSPHRASE", "passphrase");
+        cmd.output().expect("command should've been executed");
+    }
+    let mut lock_cmd = fx.cmd(["lock"]);
+    lock_cmd
+        .output()
+        .expect("lock command should've been executed");
+
+    let mut cmd = fx.cmd(["ls"]);
+    cmd.env("TASH_PASSPHRASE", "incorrect");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't open stash: couldn't decrypt the stash's key: couldn't decrypt content: Decryption failed
+    ");
+}
+
+#[test]
+fn fails_if_unlock_timeout_is_too_long() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut cmd = fx.cmd(["unlock", "--timeout", "18446744073709551615s"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't unlock stash: timeout is too long
+    ");
+    assert!(fx.runtime_dir_entries().is_empty());
+}
+
+#[test]
+fn fails_if_unlocked_session_has_expired() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+    let mut unlock_cmd = fx.cmd(["unlock", "--timeout", "0s"]);
+    unlock_cmd.env("TASH_PASSPHRASE", "passphrase");
+    unlock_cmd
+        .output()
+        .expect("unlock command should've been executed");
+
+    let mut cmd = fx.cmd(["ls"]);
+    cmd.env("TASH_PASSPHRASE", "incorrect");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't open stash: couldn't decrypt the stash's key: couldn't decrypt content: Decryption failed
+    ");
+}
+
+#[test]
+fn fails_if_stash_is_already_encrypted() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut cmd = fx.cmd(["encrypt-stash"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't encrypt stash: stash is already encrypted
+    ");
+}
+
+#[test]
+fn fails_if_unlocking_a_stash_that_isnt_encrypted() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["unlock"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't unlock stash: stash is not encrypted
+    ");
+}
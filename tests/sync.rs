@@ -0,0 +1,696 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn setting_up_sync_pushes_existing_entries() {
+    // GIVEN
+    let fx = Fixture::new();
+    let remote = fx.create_remote("remote.git");
+    for key in ["key-a", "key-b"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+
+    let mut cmd = fx.cmd(["sync", "init"]);
+    cmd.arg(&remote);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pushed 1 commit
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.commit_subjects(), vec!["update key-a, key-b"]);
+}
+
+#[test]
+fn setting_up_sync_pulls_entries_from_the_remote() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let remote = laptop.create_remote("remote.git");
+    let mut push_cmd = laptop.cmd(["push", "key-a", "--data", "from laptop"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut init_cmd = laptop.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+
+    let dev_box = Fixture::new();
+    let mut push_cmd = dev_box.cmd(["push", "key-b", "--data", "from dev box"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = dev_box.cmd(["sync", "init"]);
+    cmd.arg(&remote);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pulled changes to: key-a
+    pushed 2 commits
+
+    ----- stderr -----
+    ");
+    assert_eq!(dev_box.stashed_content("key-a"), b"from laptop");
+    assert_eq!(dev_box.data_dir_mode("key-a"), 0o600);
+}
+
+#[test]
+fn changes_are_committed_and_synced() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let remote = laptop.create_remote("remote.git");
+    let dev_box = Fixture::new();
+    for fx in [&laptop, &dev_box] {
+        let mut init_cmd = fx.cmd(["sync", "init"]);
+        init_cmd.arg(&remote);
+        init_cmd
+            .output()
+            .expect("sync init command should've been executed");
+    }
+    let commands = vec![
+        vec!["push", "key-a", "--data", "content"],
+        vec!["push", "key-b", "--data", "content"],
+        vec!["delete", "key-a"],
+    ];
+    for args in commands {
+        let mut cmd = laptop.cmd(args);
+        cmd.output().expect("command should've been executed");
+    }
+    let mut laptop_sync_cmd = laptop.cmd(["sync"]);
+    laptop_sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+
+    let mut cmd = dev_box.cmd(["sync"]);
+
+    // WHEN
+    // THEN
+    assert_eq!(
+        laptop.commit_subjects(),
+        vec!["update key-a", "update key-b", "update key-a"]
+    );
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pulled changes to: key-b
+
+    ----- stderr -----
+    ");
+    let mut ls_cmd = dev_box.cmd(["ls"]);
+    assert_cmd_snapshot!(ls_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    key-b
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn changes_can_be_committed_in_batches() {
+    // GIVEN
+    let fx = Fixture::new();
+    let remote = fx.create_remote("remote.git");
+    fx.write_config("sync_batch_commits = true\n");
+    let mut init_cmd = fx.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+    for key in ["key-a", "key-b"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    assert!(fx.commit_subjects().is_empty());
+
+    let mut cmd = fx.cmd(["sync"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pushed 1 commit
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.commit_subjects(), vec!["update key-a, key-b"]);
+}
+
+#[test]
+fn syncing_twice_reports_nothing_new() {
+    // GIVEN
+    let fx = Fixture::new();
+    let remote = fx.create_remote("remote.git");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut init_cmd = fx.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+
+    let mut cmd = fx.cmd(["sync"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    already in sync
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn conflicts_can_be_resolved_by_preferring_a_side() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let remote = laptop.create_remote("remote.git");
+    let dev_box = Fixture::new();
+    let mut push_cmd = laptop.cmd(["push", "key", "--data", "original"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    for fx in [&laptop, &dev_box] {
+        let mut init_cmd = fx.cmd(["sync", "init"]);
+        init_cmd.arg(&remote);
+        init_cmd
+            .output()
+            .expect("sync init command should've been executed");
+    }
+    for (fx, content) in [(&laptop, "from laptop"), (&dev_box, "from dev box")] {
+        let mut push_cmd = fx.cmd(["push", "key", "--data", content]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let mut laptop_sync_cmd = laptop.cmd(["sync"]);
+    laptop_sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+
+    let mut cmd = dev_box.cmd(["sync", "--prefer", "remote"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pulled changes to: key
+    pushed 2 commits
+
+    ----- stderr -----
+    ");
+    assert_eq!(dev_box.stashed_content("key"), b"from laptop");
+}
+
+#[test]
+fn preferring_a_side_takes_entries_from_it_as_a_whole() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let remote = laptop.create_remote("remote.git");
+    let dev_box = Fixture::new();
+    let mut push_cmd = laptop.cmd(["push", "key", "--data", "line 2\nline 3\nline 4\n"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    for fx in [&laptop, &dev_box] {
+        let mut init_cmd = fx.cmd(["sync", "init"]);
+        init_cmd.arg(&remote);
+        init_cmd
+            .output()
+            .expect("sync init command should've been executed");
+    }
+    // changes that git could merge line by line
+    let mut append_cmd = laptop.cmd(["append", "key", "--data", "line 5\n"]);
+    append_cmd
+        .output()
+        .expect("append command should've been executed");
+    let mut prepend_cmd = dev_box.cmd(["prepend", "key", "--data", "line 1\n"]);
+    prepend_cmd
+        .output()
+        .expect("prepend command should've been executed");
+    let mut laptop_sync_cmd = laptop.cmd(["sync"]);
+    laptop_sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+    let mut sync_cmd = dev_box.cmd(["sync", "--prefer", "local"]);
+    sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+
+    let mut cmd = dev_box.cmd(["get", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    line 1
+    line 2
+    line 3
+    line 4
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn emptying_a_synced_stash_keeps_its_repository() {
+    // GIVEN
+    let fx = Fixture::new();
+    let remote = fx.create_remote("remote.git");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut init_cmd = fx.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+    let mut empty_cmd = fx.cmd(["empty", "-y"]);
+    empty_cmd
+        .output()
+        .expect("empty command should've been executed");
+
+    let mut cmd = fx.cmd(["doctor"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    no issues found
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries(), vec![".git"]);
+    assert_eq!(fx.commit_subjects(), vec!["update key", "update key"]);
+}
+
+#[test]
+fn syncing_with_a_dir_copies_entries_both_ways() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let share = laptop.tmp_path("share");
+    let dev_box = Fixture::new();
+    let mut push_cmd = laptop.cmd(["push", "key-a", "--data", "from laptop"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut laptop_sync_cmd = laptop.cmd(["sync", "--dir"]);
+    laptop_sync_cmd.arg(&share);
+    laptop_sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+    let mut push_cmd = dev_box.cmd(["push", "key-b", "--data", "from dev box"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = dev_box.cmd(["sync", "--dir"]);
+    cmd.arg(&share);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pulled key-a (added)
+    pushed key-b (added)
+
+    pulled 1, pushed 1, 0 conflicts
+
+    ----- stderr -----
+    ");
+    let mut laptop_sync_cmd = laptop.cmd(["sync", "--dir"]);
+    laptop_sync_cmd.arg(&share);
+    assert_cmd_snapshot!(laptop_sync_cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pulled key-b (added)
+
+    pulled 1, pushed 0, 0 conflicts
+
+    ----- stderr -----
+    ");
+    assert_eq!(laptop.stashed_content("key-b"), b"from dev box");
+}
+
+#[test]
+fn syncing_with_a_dir_syncs_deletions() {
+    // GIVEN
+    let fx = Fixture::new();
+    let share = fx.tmp_path("share");
+    for key in ["key-a", "key-b"] {
+        let mut push_cmd = fx.cmd(["push", key, "--data", "content"]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let mut sync_cmd = fx.cmd(["sync", "--dir"]);
+    sync_cmd.arg(&share);
+    sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+    let mut delete_cmd = fx.cmd(["delete", "key-a"]);
+    delete_cmd
+        .output()
+        .expect("delete command should've been executed");
+    std::fs::remove_file(share.join("key-b")).expect("file should've been removed");
+
+    let mut cmd = fx.cmd(["sync", "--dir"]);
+    cmd.arg(&share);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pushed key-a (deleted)
+    pulled key-b (deleted)
+
+    pulled 1, pushed 1, 0 conflicts
+
+    ----- stderr -----
+    ");
+    assert_eq!(fx.data_dir_entries(), vec![".meta", ".sync"]);
+    assert!(!share.join("key-a").exists());
+}
+
+#[test]
+fn syncing_with_a_dir_keeps_both_versions_of_conflicting_entries() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let share = laptop.tmp_path("share");
+    let dev_box = Fixture::new();
+    let mut push_cmd = laptop.cmd(["push", "key", "--data", "original"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    for fx in [&laptop, &dev_box] {
+        let mut sync_cmd = fx.cmd(["sync", "--dir"]);
+        sync_cmd.arg(&share);
+        sync_cmd
+            .output()
+            .expect("sync command should've been executed");
+    }
+    for (fx, content) in [(&laptop, "from laptop"), (&dev_box, "from dev box")] {
+        let mut push_cmd = fx.cmd(["push", "key", "--data", content]);
+        push_cmd
+            .output()
+            .expect("push command should've been executed");
+    }
+    let mut laptop_sync_cmd = laptop.cmd(["sync", "--dir"]);
+    laptop_sync_cmd.arg(&share);
+    laptop_sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+
+    let mut cmd = dev_box.cmd(["sync", "--prefer", "remote", "--dir"]);
+    cmd.arg(&share);
+
+    // WHEN
+    // THEN
+    insta::with_settings!({filters => vec![(r"conflict-\d+", "conflict-[TIMESTAMP]")]}, {
+        assert_cmd_snapshot!(cmd, @r"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        conflict key: kept the directory's version; this stash's version was saved as key.conflict-[TIMESTAMP]
+        pulled key (changed)
+
+        pulled 1, pushed 0, 1 conflict
+
+        ----- stderr -----
+        ");
+    });
+    assert_eq!(dev_box.stashed_content("key"), b"from laptop");
+    let conflicting_version = dev_box
+        .data_dir_entries()
+        .into_iter()
+        .find(|e| e.starts_with("key.conflict-"))
+        .expect("conflicting version should've been kept");
+    assert_eq!(
+        dev_box.stashed_content(&conflicting_version),
+        b"from dev box"
+    );
+    assert!(
+        dev_box
+            .data_dir_entries_in(".meta")
+            .contains(&format!("{conflicting_version}.json"))
+    );
+}
+
+#[test]
+fn syncing_with_a_dir_syncs_changes_to_metadata() {
+    // GIVEN
+    let fx = Fixture::new();
+    let share = fx.tmp_path("share");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut sync_cmd = fx.cmd(["sync", "--dir"]);
+    sync_cmd.arg(&share);
+    sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "content", "--sensitive"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["sync", "--dir"]);
+    cmd.arg(&share);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pushed key (changed)
+
+    pulled 0, pushed 1, 0 conflicts
+
+    ----- stderr -----
+    ");
+    let metadata = std::fs::read_to_string(share.join(".meta").join("key.json"))
+        .expect("metadata should've been read");
+    assert!(metadata.contains(r#""sensitive":true"#), "{metadata}");
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_if_sync_isnt_set_up() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["sync"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't sync stash: sync isn't set up for this stash; set it up via "tash sync init <REMOTE>"
+    "#);
+}
+
+#[test]
+fn fails_if_sync_is_already_set_up() {
+    // GIVEN
+    let fx = Fixture::new();
+    let remote = fx.create_remote("remote.git");
+    let mut init_cmd = fx.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+
+    let mut cmd = fx.cmd(["sync", "init"]);
+    cmd.arg(&remote);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't set up sync: sync is already set up for this stash
+    ");
+}
+
+#[test]
+fn fails_if_entries_changed_on_both_sides() {
+    // GIVEN
+    let laptop = Fixture::new();
+    let remote = laptop.create_remote("remote.git");
+    let dev_box = Fixture::new();
+    let mut push_cmd = laptop.cmd(["push", "key-a", "--data", "original"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    for fx in [&laptop, &dev_box] {
+        let mut init_cmd = fx.cmd(["sync", "init"]);
+        init_cmd.arg(&remote);
+        init_cmd
+            .output()
+            .expect("sync init command should've been executed");
+    }
+    let mut push_cmd = laptop.cmd(["push", "key-a", "--data", "from laptop"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut laptop_sync_cmd = laptop.cmd(["sync"]);
+    laptop_sync_cmd
+        .output()
+        .expect("sync command should've been executed");
+    let mut delete_cmd = dev_box.cmd(["delete", "key-a"]);
+    delete_cmd
+        .output()
+        .expect("delete command should've been executed");
+
+    let mut cmd = dev_box.cmd(["sync"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't sync stash: entries changed both locally and remotely: ["key-a"] (use --prefer local or --prefer remote to resolve them)
+    "#);
+    assert_eq!(dev_box.data_dir_entries(), vec![".git"]);
+}
+
+#[test]
+fn fails_to_sync_with_a_dir_holding_an_encrypted_stash() {
+    // GIVEN
+    let fx = Fixture::new();
+    let share = fx.tmp_path("share");
+    std::fs::create_dir(&share).expect("directory should've been created");
+    std::fs::write(share.join("stash.key"), "key").expect("file should've been written to");
+
+    let mut cmd = fx.cmd(["sync", "--dir"]);
+    cmd.arg(&share);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't sync stash with directory: directory to sync with holds an encrypted stash; encrypted stashes can only be synced via git
+    ");
+}
+
+#[test]
+fn fails_if_an_encrypted_stash_is_synced_with_a_dir() {
+    // GIVEN
+    let fx = Fixture::new();
+    let share = fx.tmp_path("share");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "hunter2"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut encrypt_cmd = fx.cmd(["encrypt-stash"]);
+    encrypt_cmd.env("TASH_PASSPHRASE", "passphrase");
+    encrypt_cmd
+        .output()
+        .expect("encrypt-stash command should've been executed");
+
+    let mut cmd = fx.cmd(["sync", "--dir"]);
+    cmd.arg(&share).env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't sync stash with directory: this stash is encrypted; encrypted stashes can only be synced via git
+    ");
+    assert!(!share.exists());
+}
+
+#[test]
+fn fails_if_a_stash_synced_via_git_is_encrypted() {
+    // GIVEN
+    let fx = Fixture::new();
+    let remote = fx.create_remote("remote.git");
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "hunter2"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    let mut init_cmd = fx.cmd(["sync", "init"]);
+    init_cmd.arg(&remote);
+    init_cmd
+        .output()
+        .expect("sync init command should've been executed");
+
+    let mut cmd = fx.cmd(["encrypt-stash"]);
+    cmd.env("TASH_PASSPHRASE", "passphrase");
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't encrypt stash: stash is synced via git, and its history (and remote) would still hold its entries and keys in plaintext; encrypt the stash before setting up sync instead
+    ");
+    assert_eq!(fx.stashed_content("key"), b"hunter2");
+}
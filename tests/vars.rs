@@ -0,0 +1,106 @@
+mod common;
+use common::Fixture;
+use insta_cmd::assert_cmd_snapshot;
+
+//-------------//
+//  SUCCESSES  //
+//-------------//
+
+#[test]
+fn listing_template_vars_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd([
+        "push",
+        "key",
+        "--data",
+        "curl -H 'Authorization: {{token}}' https://{{host}}/api/{{ version }}?host={{host}}",
+    ]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["vars", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    token
+    host
+    version
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn listing_template_vars_for_content_without_any_works() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--file-path", "tests/static/sample.txt"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+
+    let mut cmd = fx.cmd(["vars", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+}
+
+//------------//
+//  FAILURES  //
+//------------//
+
+#[test]
+fn fails_if_key_doesnt_exist() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut cmd = fx.cmd(["vars", "non-existent-key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't list template variables: key doesn't exist in stash
+    ");
+}
+
+#[test]
+fn fails_if_content_doesnt_match_its_checksum() {
+    // GIVEN
+    let fx = Fixture::new();
+    let mut push_cmd = fx.cmd(["push", "key", "--data", "echo content"]);
+    push_cmd
+        .output()
+        .expect("push command should've been executed");
+    fx.write_data_dir_file("key", "echo corrupted");
+
+    let mut cmd = fx.cmd(["vars", "key"]);
+
+    // WHEN
+    // THEN
+    assert_cmd_snapshot!(cmd, @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error: couldn't list template variables: content doesn't match the checksum recorded when it was stashed; it may be corrupted
+    ");
+    assert_eq!(fx.stashed_content("key"), b"echo corrupted");
+}